@@ -1,17 +1,48 @@
-use crate::types::Hand;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+use crate::{holecarding::HoleCardTell, money::Money, shuffle::ShuffleRoutine, sidebets::{Buster, CountTags, LuckyLadies, OverUnder13, PerfectPairs, RoyalMatch, SideBet, TwentyOnePlusThree}, types::{Card, CardFace, Hand, Suit}};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameConfig {
+    /// The game being dealt, the strategies play the chart for it
+    pub variant: GameVariant,
+
     /// Number of decks in the shoe
     pub reserve_decks: usize,
+    /// Which cards make up each deck
+    pub deck_type: DeckType,
+    /// Number of hands the player is dealt, each with the same wager
+    pub starting_hands: usize,
+    /// Whether the player can swap the second cards of the first two hands before playing
+    pub player_can_switch: bool,
+    /// How the cards are shuffled between rounds
+    pub shoe_rules: ShoeRules,
+    /// How the dealer shuffles the discards when the shoe is shuffled by hand
+    pub shuffle_routine: ShuffleRoutine,
 
     /// Dealer rules for hitting on soft 17
     pub dealer_rules: DealerRules,
-    /// Dealer checks for blackjack
-    pub dealer_checks_for_blackjack: bool,
+    /// Whether the dealer takes a hole card and peeks for blackjack
+    pub hole_card_rules: HoleCardRules,
+    /// Whether both of the dealer's cards are dealt face up
+    pub dealer_cards_exposed: bool,
+    /// Whether a dealer bust on exactly 22 pushes every hand except a blackjack
+    pub dealer_22_pushes: bool,
+    /// Whether the dealer wins tied hands, tied blackjacks still go to the player
+    pub dealer_wins_ties: bool,
+    /// How often the player gets to learn something about the dealer's hole card
+    pub information_leakage: InformationLeakage,
 
     /// Payout odds for blackjack
     pub payout_odds: PayoutOdds,
+    /// Whether a player 21 wins even against a dealer 21 or blackjack
+    pub player_21_always_wins: bool,
+    /// Number of cards that wins a hand outright if it hasn't busted, five for a five card
+    /// Charlie
+    pub player_charlie: Option<usize>,
+    /// Extra payouts for winning hands made of particular cards
+    pub hand_bonuses: HandBonuses,
 
     // Double Down
 
@@ -19,11 +50,26 @@ pub struct GameConfig {
     pub doubling_down_rules: DoublingDownRules,
     /// Whether the player can double down after splitting
     pub player_can_double_after_split: bool,
+    /// Whether the player can double down on more than two cards
+    pub player_can_double_any_number_of_cards: bool,
+    /// Whether the player can double for less than the original bet
+    pub player_can_double_for_less: bool,
+    /// Whether the player can give up a doubled hand and only lose the original bet
+    pub player_can_rescue: bool,
+    /// Number of times a doubled hand can be doubled again after its next card
+    pub player_redoubles: usize,
+    /// Whether the casino puts up the double on a hard 9, 10 or 11 for free
+    pub free_doubles: bool,
+    /// Whether the casino puts up the bet for splitting any pair but tens for free
+    pub free_splits: bool,
 
     // Splitting
 
-    /// Maximum number of splits a player can make
+    /// Maximum number of splits a player can make on each starting hand, so each one can
+    /// become one more hand than this
     pub player_splits: usize,
+    /// Which pairs of cards can be split
+    pub split_matching: SplitMatching,
     /// Whether the player can resplit aces
     pub player_can_resplit_aces: bool,
     /// Whether the player can hit split aces
@@ -32,34 +78,129 @@ pub struct GameConfig {
     // Surrender
     /// Surrender rules for the player
     pub surrender_rules: SurrenderRules,
+
+    // Side bets
+
+    /// Side bets the player makes every round
+    pub side_bets: Vec<Box<dyn SideBet>>,
+    /// Amount staked on each side bet
+    pub side_bet_wager: Money,
+    /// A count kept for timing the side bets, their results are tracked by it instead of Hi-Lo
+    pub side_count: Option<CountTags>,
 }
 
 impl GameConfig {
+    pub fn from_args(args: &[String]) -> Self {
+        let mut config = if args.contains(&String::from("--spanish21")) {
+            Self::spanish_21()
+        } else if args.contains(&String::from("--switch")) {
+            Self::blackjack_switch()
+        } else if args.contains(&String::from("--free-bet")) {
+            Self::free_bet()
+        } else if args.contains(&String::from("--double-exposure")) {
+            Self::double_exposure()
+        } else {
+            Self::default()
+        };
+        if let Some(reserve_cards) = count_arg(args, "--csm", 20) {
+            config.shoe_rules = ShoeRules::ContinuousShuffle { reserve_cards };
+        } else if args.contains(&String::from("--infinite")) {
+            config.shoe_rules = ShoeRules::InfiniteDeck;
+        }
+        if args.contains(&String::from("--riffle")) {
+            config.shuffle_routine = ShuffleRoutine::riffle_strip_riffle();
+        } else if args.contains(&String::from("--one-pass")) {
+            config.shuffle_routine = ShuffleRoutine::one_pass();
+        } else if args.contains(&String::from("--box")) {
+            config.shuffle_routine = ShuffleRoutine::box_and_riffle();
+        }
+        // Flags only switch rules on, so they can't undo a variant's own rules
+        config.player_can_double_any_number_of_cards |= args.contains(&String::from("--double-any-cards"));
+        config.player_can_double_for_less |= args.contains(&String::from("--double-for-less"));
+        config.player_can_rescue |= args.contains(&String::from("--rescue"));
+        config.player_can_resplit_aces |= args.contains(&String::from("--resplit-aces"));
+        config.player_can_hit_split_aces |= args.contains(&String::from("--hit-split-aces"));
+        if args.contains(&String::from("--split-same-rank")) {
+            config.split_matching = SplitMatching::SameRank;
+        }
+        if args.contains(&String::from("--early-surrender")) {
+            config.surrender_rules = SurrenderRules::EarlySurrender;
+        } else if args.contains(&String::from("--no-surrender")) {
+            config.surrender_rules = SurrenderRules::NoSurrender;
+        }
+        if args.contains(&String::from("--enhc")) {
+            config.hole_card_rules = HoleCardRules::NoHoleCard(NoHoleCardLosses::AllBets);
+        } else if args.contains(&String::from("--obo")) {
+            config.hole_card_rules = HoleCardRules::NoHoleCard(NoHoleCardLosses::OriginalBetsOnly);
+        }
+        if let Some((win, per)) = ratio_arg(args, "--blackjack-pays") {
+            config.payout_odds = PayoutOdds::Ratio { win, per };
+        }
+        // Fewer cards would win every hand outright, so a Charlie takes at least three
+        if let Some(cards) = count_arg(args, "--charlie", 5).filter(|&cards| cards >= 3) {
+            config.player_charlie = Some(cards);
+        }
+        if args.contains(&String::from("--678-777")) {
+            config.hand_bonuses = HandBonuses::six_seven_eight_and_sevens();
+        }
+        if args.contains(&String::from("--21+3")) {
+            config.side_bets.push(Box::new(TwentyOnePlusThree::default()));
+        }
+        if args.contains(&String::from("--perfect-pairs")) {
+            config.side_bets.push(Box::new(PerfectPairs::default()));
+        }
+        if args.contains(&String::from("--lucky-ladies")) {
+            config.side_bets.push(Box::new(LuckyLadies::default()));
+        }
+        if args.contains(&String::from("--royal-match")) {
+            config.side_bets.push(Box::new(RoyalMatch::default()));
+        }
+        if args.contains(&String::from("--over-13")) {
+            config.side_bets.push(Box::new(OverUnder13::over()));
+        } else if args.contains(&String::from("--under-13")) {
+            config.side_bets.push(Box::new(OverUnder13::under()));
+        }
+        if args.contains(&String::from("--buster")) {
+            config.side_bets.push(Box::new(Buster::default()));
+        }
+        if let Some(tags) = tags_arg(args, "--side-count") {
+            config.side_count = Some(CountTags { name: "Side count".to_string(), tags });
+        }
+        if let Some(probability) = probability_arg(args, "--hole-card") {
+            config.information_leakage = InformationLeakage::HoleCard { probability };
+        } else if let Some(probability) = probability_arg(args, "--warped") {
+            config.information_leakage = InformationLeakage::WarpedTens { probability };
+        }
+        config
+    }
     pub fn dealer_should_hit(&self, dealer_hand: &Hand) -> bool {
         let soft_17 = dealer_hand.is_soft() && dealer_hand.value() == 17;
 
         match self.dealer_rules {
-            DealerRules::StandOnSoft17 => dealer_hand.value() < 17 || soft_17,
-            DealerRules::HitOnSoft17 => dealer_hand.value() < 17,
+            DealerRules::StandOnSoft17 => dealer_hand.value() < 17,
+            DealerRules::HitOnSoft17 => dealer_hand.value() < 17 || soft_17,
         }
     }
     pub fn player_can_split(&self, hands: &[Hand], current_hand: usize) -> bool {
-        if hands.len() >= self.player_splits {
+        let Some(hand) = hands.get(current_hand) else {
+            return false; // No hands to split
+        };
+        let from_same_hand = hands.iter().filter(|other| other.starting_hand == hand.starting_hand).count();
+        if from_same_hand > self.player_splits {
             return false; // Cannot split more than allowed
         }
-        if hands.is_empty() {
-            return false; // No hands to split
+        if hand.is_split_aces() && !self.player_can_resplit_aces {
+            return false; // Split aces can't be split again
         }
-        let last_hand = &hands[current_hand];
-        last_hand.can_split() // Check if the last hand can be split
+        hand.can_split(&self.split_matching)
     }
 
     pub fn player_can_double_down(&self, hands: &[Hand], current_hand: usize) -> bool {
         let hand = &hands[current_hand];
-        if hand.cards.len() != 2 {
-            return false; // Can only double down on two cards
+        if hand.cards.len() < 2 || (hand.cards.len() > 2 && !self.player_can_double_any_number_of_cards) {
+            return false; // Can only double down on two cards unless the table allows more
         }
-        if hands.len() > 1 && !self.player_can_double_after_split {
+        if hand.from_split && !self.player_can_double_after_split {
             return false; // Cannot double down after splitting if not allowed
         }
         match self.doubling_down_rules {
@@ -71,13 +212,77 @@ impl GameConfig {
         }
     }
     pub fn player_can_surrender(&self, hands: &[Hand]) -> bool {
-        if hands.is_empty() {
-            return false; // No hands to surrender
+        if hands.len() != 1 || hands[0].cards.len() != 2 {
+            return false; // Surrender is only the first decision on the first two cards
         }
         match self.surrender_rules {
             SurrenderRules::NoSurrender => false, // Surrender not allowed
-            SurrenderRules::EarlySurrender => true, // Can surrender at any time
-            SurrenderRules::LateSurrender => true, // Can surrender after dealer checks for blackjack
+            SurrenderRules::EarlySurrender => true, // Offered before the peek too, and again after it
+            SurrenderRules::LateSurrender => true, // The dealer has already checked for blackjack
+        }
+    }
+
+    /// Spanish 21 dealt from six Spanish decks with the usual Las Vegas rules
+    ///
+    /// Taking out the tens costs the player about 2%, the liberal rules and bonuses give most
+    /// of it back.
+    pub fn spanish_21() -> Self {
+        Self {
+            variant: GameVariant::Spanish21,
+            deck_type: DeckType::Spanish,
+            dealer_rules: DealerRules::HitOnSoft17,
+            player_21_always_wins: true,
+            hand_bonuses: HandBonuses::Spanish21,
+            player_can_double_any_number_of_cards: true,
+            player_can_rescue: true,
+            player_redoubles: 3,
+            player_can_resplit_aces: true,
+            surrender_rules: SurrenderRules::LateSurrender,
+            ..Self::default()
+        }
+    }
+
+    /// Blackjack Switch, two hands whose second cards can be swapped, paid for by a dealer
+    /// 22 pushing and blackjack paying even money
+    pub fn blackjack_switch() -> Self {
+        Self {
+            variant: GameVariant::BlackjackSwitch,
+            starting_hands: 2,
+            player_can_switch: true,
+            dealer_rules: DealerRules::HitOnSoft17,
+            dealer_22_pushes: true,
+            payout_odds: PayoutOdds::EvenMoney,
+            surrender_rules: SurrenderRules::NoSurrender,
+            ..Self::default()
+        }
+    }
+
+    /// Free Bet Blackjack, the casino fronts the extra bet on most doubles and splits and
+    /// makes it back with a dealer 22 pushing
+    pub fn free_bet() -> Self {
+        Self {
+            variant: GameVariant::FreeBet,
+            dealer_rules: DealerRules::HitOnSoft17,
+            dealer_22_pushes: true,
+            free_doubles: true,
+            free_splits: true,
+            surrender_rules: SurrenderRules::NoSurrender,
+            ..Self::default()
+        }
+    }
+
+    /// Double Exposure, both dealer cards are face up but the dealer wins ties and blackjack
+    /// only pays even money
+    pub fn double_exposure() -> Self {
+        Self {
+            variant: GameVariant::DoubleExposure,
+            dealer_rules: DealerRules::HitOnSoft17,
+            dealer_cards_exposed: true,
+            dealer_wins_ties: true,
+            payout_odds: PayoutOdds::EvenMoney,
+            doubling_down_rules: DoublingDownRules::DoubleOnlyOn9To11,
+            surrender_rules: SurrenderRules::NoSurrender,
+            ..Self::default()
         }
     }
 }
@@ -85,54 +290,296 @@ impl GameConfig {
 impl Default for GameConfig {
     fn default() -> Self {
         Self {
+            variant: GameVariant::Standard,
+
             // Standard 6-deck shoe
             reserve_decks: 6,
+            deck_type: DeckType::Standard,
+            starting_hands: 1,
+            player_can_switch: false,
+            // Dealer shuffles by hand once the cut card comes out
+            shoe_rules: ShoeRules::HandShuffled,
+            shuffle_routine: ShuffleRoutine::Uniform,
             
             // More common house rule (slightly favors house)
             dealer_rules: DealerRules::StandOnSoft17,
             
             // Standard peek rule for player protection
-            dealer_checks_for_blackjack: true,
+            hole_card_rules: HoleCardRules::AmericanPeek,
+            dealer_cards_exposed: false,
+            dealer_22_pushes: false,
+            dealer_wins_ties: false,
+            // A professional dealer gives nothing away
+            information_leakage: InformationLeakage::None,
             
             // Traditional blackjack payout
             payout_odds: PayoutOdds::ThreeToTwo,
+            player_21_always_wins: false,
+            player_charlie: None,
+            hand_bonuses: HandBonuses::None,
             
             // Liberal doubling rules
             doubling_down_rules: DoublingDownRules::DoubleAny,
             player_can_double_after_split: true,
+            player_can_double_any_number_of_cards: false,
+            player_can_double_for_less: false,
+            player_can_rescue: false, // Only found in a few games
+            player_redoubles: 0,
+            free_doubles: false,
+            free_splits: false,
             
             // Standard splitting rules
             player_splits: 3, // Can create up to 4 hands total
+            split_matching: SplitMatching::SameValue, // Most casinos let any two tens be split
             player_can_resplit_aces: false, // Most casinos don't allow this
             player_can_hit_split_aces: false, // Very rare to allow this
             
             // Late surrender is fairly common
             surrender_rules: SurrenderRules::LateSurrender,
+
+            // No side bets unless asked for
+            side_bets: Vec::new(),
+            side_bet_wager: Money::from_dollars(10),
+            side_count: None,
+        }
+    }
+}
+
+/// Reads the probability following a flag, a flag on its own means every round
+fn probability_arg(args: &[String], flag: &str) -> Option<f64> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    let probability = args.get(index + 1)
+        .and_then(|value| value.parse::<f64>().ok())
+        .unwrap_or(1.0);
+    Some(probability.clamp(0.0, 1.0))
+}
+
+/// Reads odds like `7:5` following a flag
+fn ratio_arg(args: &[String], flag: &str) -> Option<(u32, u32)> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    let (win, per) = args.get(index + 1)?.split_once(':')?;
+    let per: u32 = per.parse().ok()?;
+    (per > 0).then_some((win.parse().ok()?, per))
+}
+
+/// Reads ten comma separated count tags following a flag, for 2 through 9, tens and aces
+fn tags_arg(args: &[String], flag: &str) -> Option<[isize; 10]> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    let tags: Vec<isize> = args.get(index + 1)?
+        .split(',')
+        .map(|tag| tag.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    tags.try_into().ok()
+}
+
+/// Reads the number following a flag, a flag on its own means `default`
+fn count_arg(args: &[String], flag: &str, default: usize) -> Option<usize> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    let count = args.get(index + 1)
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(default);
+    Some(count)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum GameVariant {
+    /// Ordinary blackjack
+    Standard,
+    /// Spanish 21, played without the ten-rank number cards
+    Spanish21,
+    /// Blackjack Switch, two hands with the second cards swapped if the player likes
+    BlackjackSwitch,
+    /// Free Bet Blackjack, with free doubles and splits
+    FreeBet,
+    /// Double Exposure, with both dealer cards face up
+    DoubleExposure,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub enum DeckType {
+    /// All 52 cards
+    #[default]
+    Standard,
+    /// A Spanish deck of 48 cards, the four ten-rank number cards are taken out and the
+    /// face cards are the only ten-valued cards left
+    Spanish,
+}
+
+impl DeckType {
+    /// Number of cards in one deck
+    pub fn cards(&self) -> usize {
+        match self {
+            DeckType::Standard => 52,
+            DeckType::Spanish => 48,
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum PayoutOdds {
+    /// Pays $30 for a $10 bet
+    ThreeToOne,
+    /// Pays $20 for a $10 bet
+    TwoToOne,
     /// Pays $15 for a $10 bet
     ThreeToTwo,
     /// Pays $12 for a $10 bet
     SixToFive,
     /// Pays $10 for a $10 bet
     EvenMoney,
+    /// Pays `win` for every `per` bet, like 7:5
+    Ratio { win: u32, per: u32 },
 }
 
 impl PayoutOdds {
-    pub fn winning_amount(&self, bet: u32) -> u32 {
+    /// The odds as `win` for every `per` bet
+    pub fn ratio(&self) -> (u32, u32) {
+        match self {
+            PayoutOdds::ThreeToOne => (3, 1),
+            PayoutOdds::TwoToOne => (2, 1),
+            PayoutOdds::ThreeToTwo => (3, 2),
+            PayoutOdds::SixToFive => (6, 5),
+            PayoutOdds::EvenMoney => (1, 1),
+            PayoutOdds::Ratio { win, per } => (*win, *per),
+        }
+    }
+
+    /// Winnings on `bet`, rounded down to the cent
+    pub fn winning_amount(&self, bet: Money) -> Money {
+        let (win, per) = self.ratio();
+        bet.times(win, per)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum HandBonuses {
+    /// Every winning hand pays even money
+    None,
+    /// Spanish 21 bonuses for a 21 of five or more cards and for 6-7-8 or 7-7-7, paid on
+    /// hands that weren't doubled
+    Spanish21,
+    /// A table of bonuses paid on hands that weren't doubled, a hand making more than one is
+    /// paid the best of them
+    Table(Vec<HandBonus>),
+}
+
+impl HandBonuses {
+    /// The old-style bonuses for a suited 6-7-8 and for 7-7-7, more when suited
+    pub fn six_seven_eight_and_sevens() -> Self {
+        HandBonuses::Table(vec![
+            HandBonus { hand: BonusHand::SixSevenEight(BonusSuits::Suited), odds: PayoutOdds::TwoToOne },
+            HandBonus { hand: BonusHand::SevenSevenSeven(BonusSuits::Mixed), odds: PayoutOdds::ThreeToTwo },
+            HandBonus { hand: BonusHand::SevenSevenSeven(BonusSuits::Suited), odds: PayoutOdds::TwoToOne },
+        ])
+    }
+
+    /// The odds a winning hand is paid at instead of even money
+    pub fn bonus(&self, hand: &Hand, doubled: bool) -> Option<PayoutOdds> {
+        match self {
+            HandBonuses::None => None,
+            HandBonuses::Table(bonuses) => {
+                if doubled {
+                    return None;
+                }
+                bonuses.iter()
+                    .filter(|bonus| bonus.hand.matches(hand))
+                    .map(|bonus| bonus.odds.clone())
+                    .max_by_key(|odds| odds.winning_amount(Money::from_dollars(100)))
+            }
+            HandBonuses::Spanish21 => {
+                if doubled || hand.value() != 21 {
+                    return None;
+                }
+                match hand.cards.len() {
+                    0..=2 => None,
+                    3 => spanish_21_three_card_bonus(&hand.cards),
+                    4 => None,
+                    5 => Some(PayoutOdds::ThreeToTwo),
+                    6 => Some(PayoutOdds::TwoToOne),
+                    _ => Some(PayoutOdds::ThreeToOne),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HandBonus {
+    pub hand: BonusHand,
+    /// What a winning hand of this kind pays instead of even money
+    pub odds: PayoutOdds,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum BonusHand {
+    /// A 21 of at least this many cards
+    TwentyOne(usize),
+    /// A hand of at least this many cards that hasn't busted
+    Cards(usize),
+    /// A three card 6-7-8 in any order
+    SixSevenEight(BonusSuits),
+    /// Three sevens
+    SevenSevenSeven(BonusSuits),
+}
+
+impl BonusHand {
+    pub fn matches(&self, hand: &Hand) -> bool {
         match self {
-            PayoutOdds::ThreeToTwo => (bet * 3) / 2,
-            PayoutOdds::SixToFive => (bet * 6) / 5,
-            PayoutOdds::EvenMoney => bet,
+            BonusHand::TwentyOne(cards) => hand.value() == 21 && hand.cards.len() >= *cards,
+            BonusHand::Cards(cards) => !hand.is_bust() && hand.cards.len() >= *cards,
+            BonusHand::SixSevenEight(suits) => three_card_ranks(&hand.cards) == Some([6, 7, 8]) && suits.matches(&hand.cards),
+            BonusHand::SevenSevenSeven(suits) => three_card_ranks(&hand.cards) == Some([7, 7, 7]) && suits.matches(&hand.cards),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum BonusSuits {
+    /// Any suits
+    Mixed,
+    /// All the same suit
+    Suited,
+    /// All spades
+    Spades,
+}
+
+impl BonusSuits {
+    fn matches(&self, cards: &[Card]) -> bool {
+        match self {
+            BonusSuits::Mixed => true,
+            BonusSuits::Suited => cards.iter().all(|card| card.suit == cards[0].suit),
+            BonusSuits::Spades => cards.iter().all(|card| card.suit == Suit::Spades),
+        }
+    }
+}
+
+/// The ranks of a three card hand in order, face cards and aces count as 0
+fn three_card_ranks(cards: &[Card]) -> Option<[u8; 3]> {
+    let mut ranks: Vec<u8> = cards.iter()
+        .map(|card| match card.face {
+            CardFace::Number(n) => n,
+            _ => 0,
+        })
+        .collect();
+    ranks.sort();
+    ranks.try_into().ok()
+}
+
+/// 6-7-8 and 7-7-7 pay more when the cards are suited, and most in spades
+fn spanish_21_three_card_bonus(cards: &[Card]) -> Option<PayoutOdds> {
+    let ranks = three_card_ranks(cards)?;
+    if ranks != [6, 7, 8] && ranks != [7, 7, 7] {
+        return None;
+    }
+    let suited = cards.iter().all(|card| card.suit == cards[0].suit);
+    Some(match (suited, &cards[0].suit) {
+        (true, Suit::Spades) => PayoutOdds::ThreeToOne,
+        (true, _) => PayoutOdds::TwoToOne,
+        (false, _) => PayoutOdds::ThreeToTwo,
+    })
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum DealerRules {
     /// Dealer stands on soft 17
     StandOnSoft17,
@@ -140,7 +587,36 @@ pub enum DealerRules {
     HitOnSoft17,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum HoleCardRules {
+    /// The dealer takes a hole card and peeks for blackjack under an ace or ten, so the
+    /// players only act once the dealer is known not to have blackjack
+    AmericanPeek,
+    /// European no hole card: the dealer takes a second card only after the players have
+    /// acted, a dealer blackjack then takes the bets described by the losses rule
+    NoHoleCard(NoHoleCardLosses),
+}
+
+impl HoleCardRules {
+    /// True when the dealer has a hole card and checks it for blackjack before the players act
+    pub fn dealer_peeks(&self) -> bool {
+        matches!(self, HoleCardRules::AmericanPeek)
+    }
+    /// Cards dealt to the dealer before the players act
+    pub fn dealer_cards_dealt(&self) -> usize {
+        if self.dealer_peeks() { 2 } else { 1 }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum NoHoleCardLosses {
+    /// A dealer blackjack takes every bet, including doubles and splits
+    AllBets,
+    /// A dealer blackjack only takes the original bet, doubles and splits are returned
+    OriginalBetsOnly,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum DoublingDownRules {
     /// Player can double down on any two cards
     DoubleAny,
@@ -148,12 +624,79 @@ pub enum DoublingDownRules {
     DoubleOnlyOn9To11,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum SplitMatching {
+    /// Only cards of the same rank can be split, so a king can't be split from a ten
+    SameRank,
+    /// Any two cards of the same value can be split, including unmatched ten-value cards
+    SameValue,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum SurrenderRules {
-    /// Player can surrender at any time
+    /// Player can surrender before the dealer checks for blackjack, half the bet is returned
+    /// even against a dealer blackjack
     EarlySurrender,
-    /// Player can only surrender after the dealer checks for blackjack and does not have it
+    /// Player can only surrender after the dealer checks for blackjack and does not have it,
+    /// without a hole card a surrendered bet is lost in full to a dealer blackjack
     LateSurrender,
     /// Player cannot surrender
     NoSurrender,
 }
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ShoeRules {
+    /// Cards are dealt down to the cut card and then the whole shoe is shuffled
+    HandShuffled,
+    /// Discards are returned to a continuous shuffling machine after every round
+    ///
+    /// The machine keeps `reserve_cards` already shuffled cards ready to be dealt
+    ContinuousShuffle { reserve_cards: usize },
+    /// Every card is drawn from an infinite number of decks and the shoe never depletes
+    InfiniteDeck,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum InformationLeakage {
+    /// The player never learns anything about the hole card
+    None,
+    /// The dealer exposes the hole card while dealing or peeking in a `probability` of rounds
+    HoleCard { probability: f64 },
+    /// Ten valued cards are warped from peeking, in a `probability` of rounds the player
+    /// can tell whether the hole card is one of them
+    WarpedTens { probability: f64 },
+}
+
+impl InformationLeakage {
+    /// Whatever the player gets to learn about this round's hole card
+    pub fn tell(&self, hole_card: &Card) -> Option<HoleCardTell> {
+        let mut rng = thread_rng();
+        match self {
+            InformationLeakage::None => None,
+            InformationLeakage::HoleCard { probability } => {
+                rng.gen_bool(*probability).then(|| HoleCardTell::Seen(hole_card.clone()))
+            }
+            InformationLeakage::WarpedTens { probability } => {
+                rng.gen_bool(*probability).then(|| HoleCardTell::TenValued(hole_card.face_value() == 10))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(args: &[&str]) -> GameConfig {
+        let args: Vec<String> = std::iter::once("blackjack-sim").chain(args.iter().copied()).map(String::from).collect();
+        GameConfig::from_args(&args)
+    }
+
+    #[test]
+    fn charlie_takes_at_least_three_cards() {
+        assert_eq!(config(&["--charlie"]).player_charlie, Some(5));
+        assert_eq!(config(&["--charlie", "6"]).player_charlie, Some(6));
+        assert_eq!(config(&["--charlie", "2"]).player_charlie, None);
+        assert_eq!(config(&["--charlie", "0"]).player_charlie, None);
+    }
+}
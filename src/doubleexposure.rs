@@ -0,0 +1,28 @@
+use crate::{
+    game::Game,
+    holecarding::{player_odds, HoleCardTell},
+    strategy::BasicStrategy,
+    types::{Card, Hand, PlayerChoice, PlayerChoices},
+};
+
+/// Plays Double Exposure against the dealer's full two card total
+///
+/// With both cards showing the dealer's final hand is worked out from the cards left in the
+/// shoe and every play is scored against it, the same way the hole carder plays a card it
+/// saw. Losing ties means standing on totals that would push elsewhere is worth less, so
+/// the player hits and doubles far more against a dealer stiff than basic strategy does.
+pub struct DoubleExposureStrategy;
+
+impl DoubleExposureStrategy {
+    pub fn choice(game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+        let Some(hole_card) = game.dealer_exposed_card() else {
+            return BasicStrategy::choice(hand, dealer_card, choices);
+        };
+        let composition = game.shoe_composition();
+        let tell = HoleCardTell::Seen(hole_card);
+        match player_odds(game, &composition, dealer_card, Some(&tell), true) {
+            Some(mut odds) => odds.best_choice(hand, choices),
+            None => BasicStrategy::choice(hand, dealer_card, choices),
+        }
+    }
+}
@@ -11,6 +11,10 @@ pub enum Error {
     InvalidInput(String),
     /// Invalid choice
     InvalidChoice(PlayerChoice),
+    /// A recorded round played out differently when it was replayed
+    ReplayDiverged(String),
+    /// A recorded round was played under different table rules than the replay's, numbered by round
+    ReplayRulesDiffer(usize),
 }
 
 impl std::error::Error for Error {}
@@ -22,6 +26,8 @@ impl std::fmt::Display for Error {
             Error::CannotDoubleDown => "You cannot double down at this time.".to_string(),
             Error::InvalidInput(msg) => format!("Invalid input: {}", msg),
             Error::InvalidChoice(choice) => format!("Invalid choice: {}", choice),
+            Error::ReplayDiverged(msg) => format!("Replay no longer matches the history: {}", msg),
+            Error::ReplayRulesDiffer(round) => format!("Round {} was played under different table rules, replay it with the flags it was played with", round),
         };
         write!(f, "{}", error_message)
     }
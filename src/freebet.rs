@@ -0,0 +1,43 @@
+use crate::{
+    game::Game,
+    holecarding::player_odds,
+    strategy::BasicStrategy,
+    types::{Card, CardFace, Hand, PlayerChoice, PlayerChoices},
+};
+
+/// Plays Free Bet Blackjack, taking the casino's free doubles and splits
+///
+/// A free bet can only win, so nearly every one offered is worth taking. The exceptions
+/// are a pair of fives, which is better doubled as a ten, and nines against a dealer
+/// standing on a strong card. Everything else is worked out from the cards left in the
+/// shoe, which takes care of a dealer 22 pushing.
+pub struct FreeBetStrategy;
+
+impl FreeBetStrategy {
+    pub fn choice(game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+        if choices.contains(PlayerChoices::SPLIT) && game.free_split_offered() {
+            if let Some(card) = hand.splitable_card() {
+                if Self::free_split(&card, dealer_card.face_value()) {
+                    return PlayerChoice::Split;
+                }
+            }
+        }
+        if choices.contains(PlayerChoices::DOUBLE) && game.free_double_offered() {
+            return PlayerChoice::Double;
+        }
+        let composition = game.shoe_composition();
+        let peeked = game.config.hole_card_rules.dealer_peeks();
+        match player_odds(game, &composition, dealer_card, None, peeked) {
+            Some(mut odds) => odds.best_choice(hand, choices),
+            None => BasicStrategy::choice(hand, dealer_card, choices),
+        }
+    }
+
+    fn free_split(card: &CardFace, dealer_value: u8) -> bool {
+        match card {
+            CardFace::Number(5) => false, // Takes the free double instead
+            CardFace::Number(9) => !matches!(dealer_value, 7 | 10 | 11),
+            _ => true,
+        }
+    }
+}
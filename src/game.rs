@@ -1,11 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::{config::GameConfig, types::{Card, Deck, Hand, HandResult, PlayerChoice, PlayerChoices}};
+use serde::{Deserialize, Serialize};
 
-#[derive(Default)]
+use crate::{config::{GameConfig, HoleCardRules, NoHoleCardLosses, SurrenderRules}, error::Error, holecarding::HoleCardTell, money::Money, shoe::{create_shoe, Composition, Shoe, StackedShoe}, shuffle::ShuffleRecord, sidebets::SideBetResult, types::{Card, CardFace, Hand, HandResult, PlayerChoice, PlayerChoices}};
+
+#[derive(Serialize, Deserialize)]
 pub struct Game {
     /// Cards in the shoe
-    reserves: Deck,
+    reserves: Box<dyn Shoe>,
     /// Dealer's hand
     pub dealer_hand: Hand,
 
@@ -16,70 +18,213 @@ pub struct Game {
     current_hand: usize,
 
     /// The initial wager
-    initial_wager: u32,
+    initial_wager: Money,
     /// The amount the player has bet on each hand
-    player_bet: HashMap<usize, u32>,
+    player_bet: Vec<Money>,
+    /// Free bets the casino put up on each hand, they win like a bet but are never paid back
+    free_bet: Vec<Money>,
+    /// Hands the player surrendered
+    surrendered: HashSet<usize>,
+    /// Hands the player doubled down on and how many times they were doubled
+    doubled: HashMap<usize, usize>,
+    /// Doubled hands the player gave up, losing only the original bet
+    rescued: HashSet<usize>,
+    /// Set once the player has answered the early surrender offer this round
+    early_surrender_answered: bool,
+    /// Set once the player has decided whether to switch cards between the hands this round
+    switch_answered: bool,
+    /// The side bets placed this round, settled as soon as the cards are dealt or once the
+    /// dealer's hand is finished
+    side_bets: Vec<SideBetResult>,
 
-    /// Indicates if the shoe needs to be shuffled
-    shoe_needs_shuffling: bool,
+    /// Indicates if the shoe was shuffled before the current round was dealt
+    shuffled_this_round: bool,
+    /// Indicates the shoe was shuffled and the next round will be the first one dealt from it
+    shuffle_pending: bool,
+    /// Every card dealt this round in the order it came out of the shoe
+    round_cards: Vec<Card>,
+    /// What the player learned about the dealer's hole card this round
+    hole_card_tell: Option<HoleCardTell>,
 
     pub config: GameConfig,
 }
 
 impl Game {
     pub fn new(config: GameConfig) -> Self {
-        let mut this = Self {
-            config,
-            ..Default::default()
-        };
-        this.reset_game_state();
+        let shoe = create_shoe(&config);
+        Self::with_shoe(config, shoe)
+    }
 
-        this
+    /// Creates a game dealt from a stacked shoe, see [`StackedShoe::parse`] for the notation
+    ///
+    /// The game panics if the round draws more cards than the stack holds.
+    pub fn stacked(config: GameConfig, notation: &str) -> Result<Self, Error> {
+        let shoe = StackedShoe::parse(notation)?;
+        Ok(Self::with_shoe(config, Box::new(shoe)))
+    }
+
+    /// Creates a game that deals from the given shoe instead of the one in the config
+    pub fn with_shoe(config: GameConfig, mut shoe: Box<dyn Shoe>) -> Self {
+        if let Some(tags) = &config.side_count {
+            shoe.keep_side_count(tags.clone());
+        }
+        Self {
+            reserves: shoe,
+            dealer_hand: Hand::new(true),
+            player_hands: Vec::new(),
+            current_hand: 0,
+            initial_wager: Money::ZERO,
+            player_bet: Vec::new(),
+            free_bet: Vec::new(),
+            side_bets: Vec::new(),
+            surrendered: HashSet::new(),
+            doubled: HashMap::new(),
+            rescued: HashSet::new(),
+            early_surrender_answered: false,
+            switch_answered: false,
+            shuffled_this_round: false,
+            shuffle_pending: true,
+            round_cards: Vec::new(),
+            hole_card_tell: None,
+            config,
+        }
     }
 
     pub fn has_started(&self) -> bool {
-        self.initial_wager != 0
+        self.initial_wager != Money::ZERO
     }
 
-    pub fn start_game(&mut self, player_wager: u32, balance: &mut u32) {
+    pub fn start_game(&mut self, player_wager: Money, balance: &mut Money) {
         self.reset_game_state();
 
-        assert!(player_wager > 0, "Player wager must be greater than zero");
+        assert!(player_wager > Money::ZERO, "Player wager must be greater than zero");
 
-        self.player_bet.insert(0, player_wager); // Store the wager for the first hand
-        self.initial_wager = player_wager;
-        *balance -= player_wager; // Deduct the wager from the player's balance
+        self.place_wagers(player_wager, balance);
 
         // Deal initial hands
         self.deal_starting_hands();
     }
 
-    pub fn new_turn(&mut self, player_wager: u32, balance: &mut u32) {
-        self.player_bet.insert(0, player_wager); // Store the wager for the first hand
-        self.initial_wager = player_wager;
-        *balance -= player_wager; // Deduct the wager from the player's balance
+    pub fn new_turn(&mut self, player_wager: Money, balance: &mut Money) {
+        self.surrendered.clear();
+        self.doubled.clear();
+        self.rescued.clear();
+        self.early_surrender_answered = false;
+        self.switch_answered = false;
+        self.place_wagers(player_wager, balance);
         self.current_hand = 0;
 
         self.deal_starting_hands();
     }
 
-    pub fn player_payout(&self) -> u32 {
-        let mut total_winnings = 0;
-        for (i, _hand) in self.player_hands.iter().enumerate() {
-            let player_bet = *self.player_bet.get(&i).unwrap();
+    /// Puts the wager on every starting hand, replacing the bets on the last round's hands
+    /// so the bets on split hands can't carry over into the next round
+    fn place_wagers(&mut self, player_wager: Money, balance: &mut Money) {
+        self.player_bet = vec![player_wager; self.config.starting_hands]; // Store the wager for each hand
+        self.free_bet = vec![Money::ZERO; self.config.starting_hands];
+        self.initial_wager = player_wager;
+        *balance -= player_wager * self.config.starting_hands as u32; // Deduct the wagers from the player's balance
+        *balance -= self.config.side_bet_wager * self.config.side_bets.len() as u32;
+    }
+
+    pub fn player_payout(&self) -> Money {
+        let mut total_winnings = Money::ZERO;
+        for (i, (&player_bet, &free_bet)) in self.player_bet.iter().zip(&self.free_bet).enumerate() {
             match self.player_wins(i) {
                 HandResult::Blackjack => total_winnings += self.config.payout_odds.winning_amount(player_bet) + player_bet, // Blackjack pays out at the configured odds plus the original bet
-                HandResult::Win => total_winnings += player_bet * 2,
+                HandResult::Win => {
+                    // Free bets are paid as if they were the player's but aren't returned
+                    let stake = player_bet + free_bet;
+                    let bonus = self.config.hand_bonuses.bonus(&self.player_hands[i], self.doubled.contains_key(&i));
+                    total_winnings += player_bet + match bonus {
+                        Some(odds) => odds.winning_amount(stake), // Bonus hands pay more than even money
+                        None => stake,
+                    }
+                }
                 HandResult::Push => total_winnings += player_bet, // Push means no loss, return the bet
+                HandResult::Surrender => total_winnings += player_bet.times(1, 2), // Half the bet is returned
+                HandResult::Rescue => total_winnings += player_bet.saturating_sub(self.initial_wager), // The double is returned
                 HandResult::Lose => {}, // No winnings for a loss
                 HandResult::NotFinished => {} // Game not finished, no winnings yet
             }
         }
-        total_winnings
+        if self.dealer_hand.is_blackjack() && matches!(self.config.hole_card_rules, HoleCardRules::NoHoleCard(NoHoleCardLosses::OriginalBetsOnly)) {
+            // Only the original bet is lost to the dealer's blackjack, doubles and splits are returned
+            let original_bets = self.initial_wager * self.config.starting_hands as u32;
+            let main_bets: Money = self.player_bet.iter().sum();
+            total_winnings = total_winnings.max(main_bets.saturating_sub(original_bets));
+        }
+        total_winnings + self.side_bet_payout()
+    }
+
+    /// Plays out the dealer's hand, or only deals the second card, for the side bets waiting
+    /// on it, even if every hand is bust or surrendered, and returns the cards drawn
+    pub fn play_dealer_for_side_bets(&mut self) -> Vec<Card> {
+        let waiting: Vec<_> = self.side_bets.iter().zip(&self.config.side_bets)
+            .filter(|(result, _)| !result.settled)
+            .map(|(_, bet)| bet)
+            .collect();
+        if waiting.is_empty() || self.dealer_hand.is_blackjack() {
+            return Vec::new();
+        }
+        if waiting.iter().any(|bet| bet.waits_for_dealer()) {
+            self.play_dealer_hand()
+        } else {
+            self.deal_dealer_second_card()
+        }
+    }
+
+    /// Settle the side bets waiting on the dealer, who plays out the hand for them if it
+    /// isn't finished yet
+    pub fn settle_side_bets(&mut self) {
+        self.play_dealer_for_side_bets();
+        for (result, bet) in self.side_bets.iter_mut().zip(&self.config.side_bets) {
+            if !result.settled {
+                result.odds = bet.odds(&self.player_hands[0].cards, &self.dealer_hand.cards);
+                result.settled = true;
+            }
+        }
+    }
+
+    /// Net winnings on the main bets this round, leaving out the side bets
+    pub fn main_bet_net(&self) -> i64 {
+        let returned = self.player_payout() - self.side_bet_payout();
+        let wagered = self.player_total_bet() - self.side_bet_total();
+        returned.net_of(wagered)
+    }
+
+    /// Side bets placed this round and how they were settled
+    pub fn side_bets(&self) -> &[SideBetResult] {
+        &self.side_bets
+    }
+
+    /// Amount staked on side bets this round
+    pub fn side_bet_total(&self) -> Money {
+        self.side_bets.iter().map(|bet| bet.wager).sum()
+    }
+
+    /// Amount returned from side bets this round, stakes included
+    pub fn side_bet_payout(&self) -> Money {
+        self.side_bets.iter().map(SideBetResult::returned).sum()
+    }
+
+    pub fn initial_wager(&self) -> Money {
+        self.initial_wager
     }
 
-    pub fn player_total_bet(&self) -> u32 {
-        self.player_bet.values().sum()
+    /// The player's own stake on each hand
+    pub fn player_bets(&self) -> &[Money] {
+        &self.player_bet
+    }
+
+    /// The casino's free bet on each hand
+    pub fn free_bets(&self) -> &[Money] {
+        &self.free_bet
+    }
+
+    /// Everything the player has staked this round, side bets included
+    pub fn player_total_bet(&self) -> Money {
+        self.player_bet.iter().sum::<Money>() + self.side_bet_total()
     }
 
     pub fn player_wins(&self, hand: usize) -> HandResult {
@@ -90,16 +235,36 @@ impl Game {
         let player_bust = player_hand.is_bust();
         let dealer_bust = dealer_hand.is_bust();
 
+        if self.rescued.contains(&hand) {
+            return HandResult::Rescue;
+        }
+        if self.surrendered.contains(&hand) {
+            // Without a hole card a late surrender doesn't protect against the dealer's blackjack
+            let unprotected = !self.config.hole_card_rules.dealer_peeks()
+                && matches!(self.config.surrender_rules, SurrenderRules::LateSurrender);
+            if dealer_has_blackjack && unprotected {
+                return HandResult::Lose;
+            }
+            return HandResult::Surrender;
+        }
+        if self.config.player_21_always_wins && !player_bust && player_hand.value() == 21 {
+            return if player_has_blackjack { HandResult::Blackjack } else { HandResult::Win };
+        }
+
         match (player_has_blackjack, dealer_has_blackjack, player_bust, dealer_bust) {
             // Both player and dealer have blackjack
+            (true, true, _, _) if self.config.dealer_wins_ties => HandResult::Blackjack,
             (true, true, _, _) => HandResult::Push,
             // Player has blackjack, dealer does not
             (true, false, _, _) => HandResult::Blackjack,
             // Dealer has blackjack, player does not
             (false, true, _, _) => HandResult::Lose,
+            // Enough cards without busting wins whatever the dealer makes
+            (_, _, false, _) if self.is_charlie(player_hand) => HandResult::Win,
             // Player busts
             (_, _, true, _) => HandResult::Lose,
-            // Dealer busts
+            // Dealer busts, though a dealer 22 only pushes at some tables
+            (_, _, _, true) if self.config.dealer_22_pushes && dealer_hand.value() == 22 => HandResult::Push,
             (_, _, _, true) => HandResult::Win,
             // Neither busts or has blackjack
             _ => {
@@ -107,7 +272,7 @@ impl Game {
                 let dealer_value = dealer_hand.value();
                 if player_value > dealer_value {
                     HandResult::Win
-                } else if player_value < dealer_value {
+                } else if player_value < dealer_value || self.config.dealer_wins_ties {
                     HandResult::Lose
                 } else {
                     HandResult::Push
@@ -117,7 +282,7 @@ impl Game {
     }
 
     pub fn player_can_play(&self) -> bool {
-        if self.player_hands.len() == 1 && self.player_hands[0].is_blackjack() {
+        if self.player_has_blackjack() {
             return false; // Player has blackjack, no further actions needed
         }
         !self.player_choices().is_empty()
@@ -137,17 +302,9 @@ impl Game {
     }
 
     /// Returns true if the player's turn is over
-    pub fn take_turn(&mut self, choice: PlayerChoice, balance: &mut u32) {
+    pub fn take_turn(&mut self, choice: PlayerChoice, balance: &mut Money) {
         match choice {
-            PlayerChoice::Stand => {
-                self.current_hand += 1; // Move to the next hand
-                if self.player_hands.get(self.current_hand).is_some() {
-                    // We split and haven't dealt the next card yet
-                    let card = self.pop_card();
-                    let hand = &mut self.player_hands[self.current_hand];
-                    hand.push(card);
-                }
-            }
+            PlayerChoice::Stand => self.next_hand(),
             PlayerChoice::Hit => {
                 // Draw a card
                 let card = self.pop_card();
@@ -156,53 +313,199 @@ impl Game {
 
                 if hand.is_bust() {
                     println!("Hand is bust: {}", hand);
-                    self.current_hand += 1; // Move to the next hand
+                    self.next_hand();
+                } else if self.is_charlie(&self.player_hands[self.current_hand]) {
+                    self.next_hand(); // Nothing left to play for
                 }
             }
-            PlayerChoice::Double => {
-                // Double the bet, draw a card, and stand
-                self.player_bet.insert(self.current_hand, self.initial_wager * 2); // Update the bet for the current hand
-                *balance -= self.initial_wager; // Deduct the doubled bet from balance
-                
-                let card = self.pop_card();
-                let hand = &mut self.player_hands[self.current_hand];
-                hand.push(card);
-
-                if hand.is_bust() {
-                    println!("Hand is bust after doubling down: {}", hand);
-                }
-                self.current_hand += 1; // Move to the next hand after doubling down
-                if self.player_hands.get(self.current_hand).is_some() {
-                    // We split and haven't dealt the next card yet
-                    let card = self.pop_card();
-                    let hand = &mut self.player_hands[self.current_hand];
-                    hand.push(card);
-                }
+            PlayerChoice::Double => self.double_down(self.double_cost(), balance),
+            PlayerChoice::DoubleForLess(amount) => {
+                let amount = amount.clamp(Money::from_cents(1), self.initial_wager);
+                self.double_down(amount, balance);
+            }
+            PlayerChoice::Rescue => {
+                // Give up the doubled hand, the double is returned when it's settled
+                self.rescued.insert(self.current_hand);
+                self.next_hand();
             }
             PlayerChoice::Split => {
-                self.player_bet.insert(self.player_hands.len(), self.initial_wager);
-                *balance -= self.initial_wager; // Deduct the bet for the split hands
+                // The new hand is played right after the one it was split from
+                if self.free_split_offered() {
+                    self.player_bet.insert(self.current_hand + 1, Money::ZERO);
+                    self.free_bet.insert(self.current_hand + 1, self.initial_wager);
+                } else {
+                    self.player_bet.insert(self.current_hand + 1, self.initial_wager);
+                    self.free_bet.insert(self.current_hand + 1, Money::ZERO);
+                    *balance -= self.initial_wager; // Deduct the bet for the split hands
+                }
                 
                 // Split the hand into two hands
-                let hand = self.player_hands.pop().unwrap();
+                let hand = std::mem::take(&mut self.player_hands[self.current_hand]);
                 let (mut new_hand1, new_hand2) = hand.split();
                 // Deal the next card to the first new hand
                 let card1 = self.pop_card();
                 new_hand1.push(card1);
 
-                self.player_hands.push(new_hand1);
-                self.player_hands.push(new_hand2);
+                self.player_hands[self.current_hand] = new_hand1;
+                self.player_hands.insert(self.current_hand + 1, new_hand2);
+                if self.split_aces_finished() {
+                    self.next_hand();
+                }
             }
-            PlayerChoice::Surrender => {
-                // Handle surrender logic
-                // This could mean the player loses half their bet
-                self.player_bet.insert(self.current_hand, self.initial_wager / 2);
+            PlayerChoice::Surrender => self.take_turn_surrender(),
+        }
+    }
+
+    /// True while the player can still surrender before the dealer peeks under an ace or ten
+    pub fn early_surrender_offered(&self) -> bool {
+        matches!(self.config.surrender_rules, SurrenderRules::EarlySurrender)
+            && self.config.hole_card_rules.dealer_peeks()
+            && !self.early_surrender_answered
+            && self.player_hands.len() == 1
+            && !self.player_hands[0].is_blackjack()
+            && self.dealer_up_card().face_value() >= 10
+    }
+
+    /// Answers the early surrender offer, the dealer peeks afterwards either way
+    pub fn early_surrender(&mut self, surrender: bool) {
+        self.early_surrender_answered = true;
+        if surrender {
+            self.take_turn_surrender();
+        }
+    }
+
+    /// True when surrendering now returns half the bet even if the dealer has blackjack
+    pub fn surrendering_early(&self) -> bool {
+        matches!(self.config.surrender_rules, SurrenderRules::EarlySurrender)
+            && (!self.config.hole_card_rules.dealer_peeks() || self.early_surrender_offered())
+    }
+
+    /// True unless every hand is bust or surrendered, so the dealer has nothing to play against
+    pub fn dealer_needs_to_play(&self) -> bool {
+        self.player_hands.iter().enumerate()
+            .any(|(i, hand)| !hand.is_bust() && !self.surrendered.contains(&i))
+    }
+
+    /// Double the bet by `amount`, draw a card, and stand unless the hand can still be rescued
+    fn double_down(&mut self, amount: Money, balance: &mut Money) {
+        if self.free_double_offered() {
+            self.free_bet[self.current_hand] += amount; // The casino puts up the double
+        } else {
+            self.player_bet[self.current_hand] += amount; // Update the bet for the current hand
+            *balance -= amount; // Deduct the doubled bet from balance
+        }
+        *self.doubled.entry(self.current_hand).or_insert(0) += 1;
+
+        let card = self.pop_card();
+        self.player_hands[self.current_hand].push(card);
+
+        let hand = &self.player_hands[self.current_hand];
+        if hand.is_bust() {
+            println!("Hand is bust after doubling down: {}", hand);
+        } else if !self.is_charlie(hand) && (self.config.player_can_rescue || self.can_redouble()) {
+            return; // The player decides whether to stand, rescue or double the hand again
+        }
+        self.next_hand();
+    }
+
+    /// Move to the next hand, dealing it a second card if it came from a split
+    fn next_hand(&mut self) {
+        self.current_hand += 1;
+        self.ready_current_hand();
+    }
 
-                self.current_hand += 1; // Move to the next hand
+    /// Deals the current hand its second card if it came from a split, and moves on past
+    /// blackjacks and split aces that have nothing left to play
+    fn ready_current_hand(&mut self) {
+        while let Some(hand) = self.player_hands.get(self.current_hand) {
+            if hand.cards.len() < 2 {
+                // We split and haven't dealt the next card yet
+                let card = self.pop_card();
+                self.player_hands[self.current_hand].push(card);
             }
+            if !self.player_hands[self.current_hand].is_blackjack() && !self.split_aces_finished() {
+                return;
+            }
+            self.current_hand += 1;
         }
     }
 
+    /// True when the hand has enough cards to win outright, if it hasn't busted
+    fn is_charlie(&self, hand: &Hand) -> bool {
+        self.config.player_charlie.is_some_and(|cards| hand.cards.len() >= cards)
+    }
+
+    /// What doubling the current hand costs, doubling again doubles everything already on it
+    pub fn double_cost(&self) -> Money {
+        if self.doubled.contains_key(&self.current_hand) {
+            self.player_bet[self.current_hand]
+        } else {
+            self.initial_wager
+        }
+    }
+
+    /// True when doubling the current hand is on the house, a hard 9, 10 or 11 on two cards
+    pub fn free_double_offered(&self) -> bool {
+        let Some(hand) = self.player_hands.get(self.current_hand) else {
+            return false;
+        };
+        self.config.free_doubles
+            && !self.doubled.contains_key(&self.current_hand)
+            && hand.cards.len() == 2
+            && !hand.is_soft()
+            && (9..=11).contains(&hand.value())
+    }
+
+    /// True when splitting the current hand is on the house, any pair but tens
+    pub fn free_split_offered(&self) -> bool {
+        let Some(hand) = self.player_hands.get(self.current_hand) else {
+            return false;
+        };
+        self.config.free_splits
+            && hand.splitable_card().is_some_and(|card| card != CardFace::Number(10))
+    }
+
+    /// True until the player decides whether to swap the second cards of the two hands
+    pub fn switch_offered(&self) -> bool {
+        self.config.player_can_switch
+            && !self.switch_answered
+            && self.player_hands.len() == 2
+            && self.player_hands.iter().all(|hand| hand.cards.len() == 2)
+    }
+
+    /// Answers the switch offer, swapping the second cards of the two hands if asked to
+    pub fn switch(&mut self, switch: bool) {
+        self.switch_answered = true;
+        if switch {
+            let (first, second) = self.player_hands.split_at_mut(1);
+            Hand::switch(&mut first[0], &mut second[0]);
+        }
+        self.ready_current_hand();
+    }
+
+    /// Whether the current hand was doubled and can be doubled again
+    fn can_redouble(&self) -> bool {
+        self.doubled.get(&self.current_hand)
+            .is_some_and(|&doubles| doubles <= self.config.player_redoubles)
+    }
+
+    /// Split aces get a single card, so they're done once it's dealt unless they can be hit
+    /// or split again
+    fn split_aces_finished(&self) -> bool {
+        let Some(hand) = self.player_hands.get(self.current_hand) else {
+            return false;
+        };
+        hand.is_split_aces()
+            && !self.config.player_can_hit_split_aces
+            && !self.config.player_can_split(&self.player_hands, self.current_hand)
+    }
+
+    /// The player gives up the hand and gets half the bet back when it's settled
+    fn take_turn_surrender(&mut self) {
+        self.surrendered.insert(self.current_hand);
+        self.next_hand();
+    }
+
     pub fn all_player_hands_busted(&self) -> bool {
         for hand in &self.player_hands {
             if !hand.is_bust() {
@@ -213,7 +516,7 @@ impl Game {
     }
 
     pub fn play_dealer_hand(&mut self) -> Vec<Card> {
-        let mut dealer_cards = Vec::new();
+        let mut dealer_cards = self.deal_dealer_second_card();
         while self.config.dealer_should_hit(&self.dealer_hand) && self.dealer_can_hit() {
             let card = self.pop_card();
             self.dealer_hand.push(card.clone());
@@ -222,69 +525,177 @@ impl Game {
         self.dealer_hand.hide_card = false; // Dealer reveals all cards after playing
         dealer_cards
     }
+    /// Without a hole card the dealer only takes a second card once the players have acted
+    pub fn deal_dealer_second_card(&mut self) -> Vec<Card> {
+        if self.dealer_hand.cards.len() != 1 {
+            return Vec::new();
+        }
+        let card = self.pop_card();
+        self.dealer_hand.push(card.clone());
+        vec![card]
+    }
     pub fn reveal_dealer_hand(&mut self) {
         self.dealer_hand.hide_card = false; // Reveal dealer's hand
     }
     fn dealer_can_hit(&self) -> bool {
         !self.dealer_hand.is_bust()
     }
-    /// Only reveals if the dealer has blackjack if config allows it
+    /// Only reveals if the dealer has blackjack once the dealer has peeked or played
     pub fn dealer_has_blackjack(&self) -> bool {
-        if !self.config.dealer_checks_for_blackjack && self.player_can_play() {
+        if !self.config.hole_card_rules.dealer_peeks() && self.player_can_play() {
             return false;
         }
         self.dealer_hand.is_blackjack()
     }
+    /// True when every hand the player was dealt is a blackjack, so there's nothing to play
     pub fn player_has_blackjack(&self) -> bool {
         if self.player_hands.is_empty() {
             return false; // No player hands to check
         }
-        let current_hand = self.player_hands.get(self.current_hand);
-        if current_hand.is_none() {
-            return false; // No current hand to check
-        }
-        let current_hand = current_hand.unwrap();
-        current_hand.is_blackjack()
+        self.player_hands.iter().all(|hand| hand.is_blackjack())
     }
-    pub fn dealer_up_card(&self) -> Card {
-        self.dealer_hand.cards.get(1).cloned().unwrap()
+    pub fn player_hands(&self) -> &[Hand] {
+        &self.player_hands
     }
-    pub fn dealer_down_card(&self) -> Card {
+    /// Index of the hand being played, one past the last hand once they're all played
+    pub fn current_hand_index(&self) -> usize {
+        self.current_hand
+    }
+    /// The first card dealt to the dealer
+    pub fn dealer_up_card(&self) -> Card {
         self.dealer_hand.cards.first().cloned().unwrap()
     }
+    /// The dealer's second card, there's none until the dealer plays without a hole card
+    pub fn dealer_down_card(&self) -> Option<Card> {
+        self.dealer_hand.cards.get(1).cloned()
+    }
 
-    fn pop_card(&mut self) -> Card {
-        let (card, reshuffle) = self.reserves.draw();
-        self.shoe_needs_shuffling |= reshuffle;
+    /// The dealer's second card, if it's face up for everyone to see
+    pub fn dealer_exposed_card(&self) -> Option<Card> {
+        self.dealer_down_card().filter(|_| !self.dealer_hand.hide_card)
+    }
+
+    /// What the player learned about the dealer's hole card this round, if anything
+    pub fn hole_card_tell(&self) -> Option<HoleCardTell> {
+        self.hole_card_tell.clone()
+    }
 
+    /// The pile the current shoe was shuffled from, if it was rebuilt from the discards
+    pub fn last_shuffle(&self) -> Option<&ShuffleRecord> {
+        self.reserves.last_shuffle()
+    }
+
+    pub fn shuffled_this_round(&self) -> bool {
+        self.shuffled_this_round
+    }
+
+    /// Every card dealt this round in the order it came out of the shoe
+    pub fn round_cards(&self) -> &[Card] {
+        &self.round_cards
+    }
+
+    fn pop_card(&mut self) -> Card {
+        let card = self.reserves.draw();
+        self.round_cards.push(card.clone());
         card
     }
 
-    /// Set the game back to default with no hands drawn and a fresh shoe
+    /// Set the game back to default with no hands drawn
     fn reset_game_state(&mut self) {
-        self.reserves = Deck::create_shoe(self.config.reserve_decks as usize);
         self.dealer_hand = Hand::new(true);
         self.player_hands.clear();
         self.current_hand = 0;
         self.player_bet.clear();
-        self.initial_wager = 0;
-        self.shoe_needs_shuffling = false;
+        self.free_bet.clear();
+        self.side_bets.clear();
+        self.surrendered.clear();
+        self.doubled.clear();
+        self.rescued.clear();
+        self.early_surrender_answered = false;
+        self.switch_answered = false;
+        self.initial_wager = Money::ZERO;
+    }
+
+    /// Shuffles between rounds once the cut card has come out, so the new shoe can be cut
+    /// before the next bet is placed
+    pub fn prepare_next_round(&mut self) {
+        if self.reserves.needs_reshuffle() {
+            self.clear_table();
+            self.reserves.reshuffle();
+            self.shuffle_pending = true;
+        }
+    }
+
+    /// True when the shoe was just shuffled and is waiting for a player to cut it
+    pub fn awaiting_cut(&self) -> bool {
+        self.reserves.awaiting_cut()
+    }
+
+    /// Cut the shoe `position` cards from the top, or at a random spot for `None`
+    pub fn cut_shoe(&mut self, position: Option<usize>) {
+        self.reserves.cut(position);
+    }
+
+    /// The dealer picks up the players' hands first and their own last, so the discards go
+    /// into the tray in the order a real table stacks them
+    fn clear_table(&mut self) {
+        let mut discards: Vec<Card> = self.player_hands.drain(..).flat_map(|hand| hand.cards).collect();
+        discards.append(&mut self.dealer_hand.cards);
+        self.reserves.discard(discards);
     }
 
     fn deal_starting_hands(&mut self) {
-        if self.shoe_needs_shuffling {
-            self.reserves = Deck::create_shoe(self.config.reserve_decks as usize);
-            self.shoe_needs_shuffling = false; // Reset the flag after shuffling
+        // Clear the table from the last round
+        self.clear_table();
+        self.round_cards.clear();
+
+        let mut shuffled = std::mem::take(&mut self.shuffle_pending);
+        if self.reserves.needs_reshuffle() {
+            self.reserves.reshuffle();
+            shuffled = true;
         }
-        let (dealer_hand, reshuffle) = self.reserves.deal_hand(2, true);
-        self.shoe_needs_shuffling |= reshuffle;
+        self.shuffled_this_round = shuffled;
+        let true_count = self.side_true_count().unwrap_or(self.true_count());
+        let dealer_cards = self.config.hole_card_rules.dealer_cards_dealt();
+        let mut dealer_hand = self.deal_hand(dealer_cards, true);
+        dealer_hand.hide_card = !self.config.dealer_cards_exposed;
         self.dealer_hand = dealer_hand;
+        // There's nothing to glimpse when the hole card is dealt face up
+        self.hole_card_tell = self.dealer_down_card()
+            .filter(|_| self.dealer_hand.hide_card)
+            .and_then(|hole_card| self.config.information_leakage.tell(&hole_card));
 
-        let (player_hand, reshuffle) = self.reserves.deal_hand(2, false);
-        self.shoe_needs_shuffling |= reshuffle;
-        self.player_hands.clear();
+        for starting_hand in 0..self.config.starting_hands {
+            let mut player_hand = self.deal_hand(2, false);
+            player_hand.starting_hand = starting_hand;
+            self.player_hands.push(player_hand);
+        }
+        self.side_bets = self.config.side_bets.iter()
+            .map(|bet| {
+                let settled = !bet.waits_for_dealer() && (dealer_cards == 2 || !bet.needs_both_dealer_cards());
+                SideBetResult {
+                    name: bet.name().to_string(),
+                    wager: self.config.side_bet_wager,
+                    settled,
+                    odds: settled.then(|| bet.odds(&self.player_hands[0].cards, &self.dealer_hand.cards)).flatten(),
+                    true_count,
+                }
+            })
+            .collect();
         self.current_hand = 0; // Reset current hand index
-        self.player_hands.push(player_hand);
+        if !self.switch_offered() {
+            self.ready_current_hand();
+        }
+    }
+
+    fn deal_hand(&mut self, hand_size: usize, dealer: bool) -> Hand {
+        let mut hand = Hand::new(dealer);
+        for _ in 0..hand_size {
+            let card = self.pop_card();
+            hand.push(card);
+        }
+
+        hand
     }
 
     pub fn player_choices(&self) -> PlayerChoices {
@@ -294,6 +705,28 @@ impl Game {
             return choices; // No current hand to play
         }
 
+        if self.doubled.contains_key(&self.current_hand) {
+            // A doubled hand waiting to be stood, rescued or doubled again
+            choices.insert(PlayerChoices::STAND);
+            if self.config.player_can_rescue {
+                choices.insert(PlayerChoices::RESCUE);
+            }
+            if self.can_redouble() {
+                choices.insert(PlayerChoices::DOUBLE);
+            }
+            return choices;
+        }
+
+        let hand = &self.player_hands[self.current_hand];
+        if hand.is_split_aces() && hand.cards.len() == 2 && !self.config.player_can_hit_split_aces {
+            // Split aces only get one card, but may be split again
+            choices.insert(PlayerChoices::STAND);
+            if self.config.player_can_split(&self.player_hands, self.current_hand) {
+                choices.insert(PlayerChoices::SPLIT);
+            }
+            return choices;
+        }
+
         // Always allow hit and stand
         choices.insert(PlayerChoices::HIT);
         choices.insert(PlayerChoices::STAND);
@@ -303,6 +736,9 @@ impl Game {
         }
         if self.config.player_can_double_down(&self.player_hands, self.current_hand) {
             choices.insert(PlayerChoices::DOUBLE);
+            if self.config.player_can_double_for_less {
+                choices.insert(PlayerChoices::DOUBLE_FOR_LESS);
+            }
         }
         if self.config.player_can_surrender(&self.player_hands) {
             choices.insert(PlayerChoices::SURRENDER);
@@ -310,63 +746,366 @@ impl Game {
 
         choices
     }
-    pub fn cards_left(&self) -> usize {
-        self.reserves.cards.len()
+    /// Cards left to deal, `None` for an infinite deck
+    pub fn cards_left(&self) -> Option<usize> {
+        self.reserves.cards_remaining()
     }
-    pub fn decks_left(&self) -> isize {
-        (self.cards_left() / 52) as isize
+    /// Whole decks left to deal, `None` for an infinite deck
+    pub fn decks_left(&self) -> Option<isize> {
+        self.cards_left().map(|cards| (cards / self.config.deck_type.cards()) as isize)
     }
     pub fn running_count(&self) -> isize {
-        self.reserves.count
+        self.reserves.running_count()
+    }
+    /// Cards left in the shoe grouped by value
+    pub fn shoe_composition(&self) -> Composition {
+        self.reserves.composition()
+    }
+    /// True count of the side count, if the table keeps one
+    pub fn side_true_count(&self) -> Option<isize> {
+        self.config.side_count.as_ref()?;
+        match self.decks_left() {
+            None | Some(0) => Some(0),
+            Some(decks_left) => Some(self.reserves.side_running_count() / decks_left),
+        }
     }
     pub fn true_count(&self) -> isize {
-        let decks_left = self.decks_left();
-        if decks_left == 0 {
-            return 0; // Avoid division by zero
+        match self.decks_left() {
+            None | Some(0) => 0, // Avoid division by zero
+            Some(decks_left) => self.running_count() / decks_left,
         }
-        self.running_count() / decks_left
     }
 }
 
 impl std::fmt::Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "=== BLACKJACK ===")?;
-        writeln!(f, "Wager: ${}", self.initial_wager)?;
+        writeln!(f, "Wager: {}", self.initial_wager)?;
         writeln!(f)?;
         
         // Dealer's hand
         writeln!(f, "DEALER:")?;
-        if self.dealer_hand.dealer_show_card().is_blackjack_card() && !self.dealer_hand.is_blackjack() {
+        if self.dealer_hand.cards.is_empty() {
+            writeln!(f, "  {}", self.dealer_hand)?;
+        } else if self.config.hole_card_rules.dealer_peeks() && self.dealer_hand.dealer_show_card().is_blackjack_card() && !self.dealer_hand.is_blackjack() {
             writeln!(f, "  {} (Not blackjack)", self.dealer_hand)?;
         } else {
             writeln!(f, "  {}", self.dealer_hand)?;
         }
+        if let Some(tell) = self.hole_card_tell.as_ref().filter(|_| self.dealer_hand.hide_card) {
+            writeln!(f, "  You caught a glimpse of the hole card: {}", tell)?;
+        }
         writeln!(f)?;
         
         // Player's hands
         if self.player_hands.len() == 1 {
             writeln!(f, "PLAYER")?;
-            writeln!(f, "  {}", self.player_hands[0])?;
+            writeln!(f, "  {}{}", self.player_hands[0], FreeBet(self.free_bet[0]))?;
         } else {
             writeln!(f, "PLAYER HANDS:")?;
             for (i, hand) in self.player_hands.iter().enumerate() {
-                let wager = self.player_bet.get(&i).unwrap();
+                let wager = self.player_bet[i];
                 let marker = if i == self.current_hand { " <- CURRENT" } else { "" };
-                writeln!(f, "  ${} Hand {}: {}{}", wager, i + 1, hand, marker)?;
+                writeln!(f, "  {} Hand {}: {}{}{}", wager, i + 1, hand, FreeBet(self.free_bet[i]), marker)?;
+            }
+        }
+        if !self.side_bets.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "SIDE BETS:")?;
+            for bet in &self.side_bets {
+                writeln!(f, "  {}", bet)?;
             }
         }
         writeln!(f)?;
         
         // Game status
-        let cards_left = self.reserves.cards.len();
-        let decks_left = (cards_left / 52) as isize;
-        let running_count = self.reserves.count;
-        let true_count = running_count / decks_left;
-        writeln!(f, "Cards remaining: {}, Running Count: {}, True Count: {}, Decks left {}", cards_left, running_count, true_count, decks_left)?;
-        if self.shoe_needs_shuffling {
+        let running_count = self.running_count();
+        let true_count = self.true_count();
+        match (self.cards_left(), self.decks_left()) {
+            (Some(cards_left), Some(decks_left)) => {
+                writeln!(f, "Cards remaining: {}, Running Count: {}, True Count: {}, Decks left {}", cards_left, running_count, true_count, decks_left)?;
+            }
+            _ => writeln!(f, "Infinite deck, Running Count: {}, True Count: {}", running_count, true_count)?,
+        }
+        if self.reserves.needs_reshuffle() {
             writeln!(f, "⚠️  SHUFFLE NEEDED")?;
         }
         
         Ok(())
     }
 }
+
+/// Shows the free bet on a hand, if the casino put one up
+struct FreeBet(Money);
+
+impl std::fmt::Display for FreeBet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 > Money::ZERO {
+            write!(f, " (free bet {})", self.0)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::DealerRules, sidebets::{LuckyLadies, Odds}};
+
+    const BET: Money = Money::from_dollars(10);
+
+    /// Deals a round from the stacked cards with $100 to play with
+    fn deal(config: GameConfig, notation: &str) -> (Game, Money) {
+        let mut game = Game::stacked(config, notation).unwrap();
+        let mut balance = Money::from_dollars(100);
+        game.start_game(BET, &mut balance);
+        (game, balance)
+    }
+
+    fn holds(hand: &Hand, notation: &str) -> bool {
+        let cards: Vec<Card> = notation.split_whitespace().map(|card| card.parse().unwrap()).collect();
+        hand.cards.len() == cards.len() && hand.cards.iter().zip(&cards).all(|(a, b)| a.is_same_card(b))
+    }
+
+    #[test]
+    fn deals_the_up_card_first() {
+        let (game, _) = deal(GameConfig::default(), "T♠ 6♥ | 8♦ 9♣");
+        assert!(game.dealer_up_card().is_same_card(&"T♠".parse().unwrap()));
+        assert!(game.dealer_down_card().unwrap().is_same_card(&"6♥".parse().unwrap()));
+        assert!(holds(&game.player_hands()[0], "8♦ 9♣"));
+    }
+
+    /// Stands on every hand against a dealer A+6 with a 4 to draw and returns the dealer's hand
+    fn dealer_on_soft_17(config: GameConfig) -> Hand {
+        let notation = if config.starting_hands == 2 { "A♠ 6♥ | 9♦ 9♣ | 9♠ 9♥ | 4♣" } else { "A♠ 6♥ | 9♦ 9♣ | 4♣" };
+        let (mut game, mut balance) = deal(config, notation);
+        if game.config.player_can_switch {
+            game.switch(false);
+        }
+        while game.player_can_play() {
+            game.take_turn(PlayerChoice::Stand, &mut balance);
+        }
+        game.play_dealer_hand();
+        game.dealer_hand
+    }
+
+    #[test]
+    fn dealer_hits_soft_17_only_when_the_rules_say_so() {
+        assert!(holds(&dealer_on_soft_17(GameConfig::default()), "A♠ 6♥"));
+        let hits_soft_17 = GameConfig { dealer_rules: DealerRules::HitOnSoft17, ..GameConfig::default() };
+        assert!(holds(&dealer_on_soft_17(hits_soft_17), "A♠ 6♥ 4♣"));
+        assert!(holds(&dealer_on_soft_17(GameConfig::spanish_21()), "A♠ 6♥ 4♣"));
+        assert!(holds(&dealer_on_soft_17(GameConfig::blackjack_switch()), "A♠ 6♥ 4♣"));
+        assert!(holds(&dealer_on_soft_17(GameConfig::free_bet()), "A♠ 6♥ 4♣"));
+        assert!(holds(&dealer_on_soft_17(GameConfig::double_exposure()), "A♠ 6♥ 4♣"));
+    }
+
+    #[test]
+    fn lucky_ladies_waits_for_the_second_dealer_card_without_a_hole_card() {
+        let mut config = GameConfig {
+            hole_card_rules: HoleCardRules::NoHoleCard(NoHoleCardLosses::OriginalBetsOnly),
+            ..GameConfig::default()
+        };
+        config.side_bets.push(Box::new(LuckyLadies::default()));
+        let (mut game, mut balance) = deal(config, "A♠ | Q♥ Q♥ | 7♣ K♣");
+        game.take_turn(PlayerChoice::Hit, &mut balance);
+        // Every hand is bust, but the dealer still takes a second card for the jackpot
+        assert_eq!(game.play_dealer_for_side_bets().len(), 1);
+        game.settle_side_bets();
+        assert_eq!(game.side_bets()[0].odds, Some(Odds::to_one(1000)));
+    }
+
+    #[test]
+    fn deals_the_second_dealer_card_last_without_a_hole_card() {
+        let config = GameConfig {
+            hole_card_rules: HoleCardRules::NoHoleCard(NoHoleCardLosses::OriginalBetsOnly),
+            ..GameConfig::default()
+        };
+        let (mut game, mut balance) = deal(config, "T♠ | 8♦ 9♣ | 7♥");
+        assert!(game.dealer_down_card().is_none());
+        assert!(holds(&game.player_hands()[0], "8♦ 9♣"));
+        game.take_turn(PlayerChoice::Stand, &mut balance);
+        game.play_dealer_hand();
+        assert!(holds(&game.dealer_hand, "T♠ 7♥"));
+    }
+
+    #[test]
+    fn splits_a_pair_into_two_hands() {
+        let (mut game, mut balance) = deal(GameConfig::default(), "T♠ 7♥ | 8♦ 8♣ | 3♠ K♥");
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert_eq!(balance, Money::from_dollars(80));
+        assert!(holds(&game.player_hands()[0], "8♦ 3♠"));
+        game.take_turn(PlayerChoice::Stand, &mut balance);
+        assert!(holds(&game.player_hands()[1], "8♣ K♥"));
+        game.take_turn(PlayerChoice::Stand, &mut balance);
+        game.play_dealer_hand();
+        assert!(matches!(game.player_wins(0), HandResult::Lose));
+        assert!(matches!(game.player_wins(1), HandResult::Win));
+        assert_eq!(game.player_payout(), Money::from_dollars(20));
+    }
+
+    #[test]
+    fn doubles_after_a_split() {
+        let (mut game, mut balance) = deal(GameConfig::default(), "T♠ 7♥ | 8♦ 8♣ | 3♠ K♥ Q♦");
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert!(game.player_choices().contains(PlayerChoices::DOUBLE));
+        game.take_turn(PlayerChoice::Double, &mut balance);
+        assert!(holds(&game.player_hands()[0], "8♦ 3♠ K♥"));
+        assert_eq!(game.current_hand_index(), 1);
+        game.take_turn(PlayerChoice::Stand, &mut balance);
+        game.play_dealer_hand();
+        assert_eq!(balance, Money::from_dollars(70));
+        assert_eq!(game.player_bets(), &[Money::from_dollars(20), BET]);
+        assert_eq!(game.player_payout(), Money::from_dollars(60));
+    }
+
+    #[test]
+    fn split_aces_get_one_card_each() {
+        let (mut game, mut balance) = deal(GameConfig::default(), "T♠ 7♥ | A♦ A♣ | 5♠ 6♥");
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert!(holds(&game.player_hands()[0], "A♦ 5♠"));
+        assert!(holds(&game.player_hands()[1], "A♣ 6♥"));
+        assert!(!game.player_can_play());
+    }
+
+    #[test]
+    fn aces_are_only_resplit_when_the_table_allows() {
+        let notation = "T♠ 7♥ | A♦ A♣ | A♠ 6♥ 9♦ 4♣";
+        let (mut game, mut balance) = deal(GameConfig::default(), notation);
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert_eq!(game.player_hands().len(), 2);
+        assert!(!game.player_can_play());
+
+        let config = GameConfig { player_can_resplit_aces: true, ..GameConfig::default() };
+        let (mut game, mut balance) = deal(config, notation);
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert_eq!(game.player_choices(), PlayerChoices::STAND | PlayerChoices::SPLIT);
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert!(holds(&game.player_hands()[0], "A♦ 6♥"));
+        assert!(holds(&game.player_hands()[1], "A♠ 9♦"));
+        assert!(holds(&game.player_hands()[2], "A♣ 4♣"));
+    }
+
+    #[test]
+    fn split_aces_are_only_hit_when_the_table_allows() {
+        let config = GameConfig { player_can_hit_split_aces: true, ..GameConfig::default() };
+        let (mut game, mut balance) = deal(config, "T♠ 7♥ | A♦ A♣ | 5♠ 4♥ 6♥");
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert_eq!(game.current_hand_index(), 0);
+        assert!(game.player_choices().contains(PlayerChoices::HIT));
+        game.take_turn(PlayerChoice::Hit, &mut balance);
+        assert!(holds(&game.player_hands()[0], "A♦ 5♠ 4♥"));
+    }
+
+    #[test]
+    fn splits_stop_at_the_most_hands_allowed() {
+        let config = GameConfig { player_splits: 1, ..GameConfig::default() };
+        let (mut game, mut balance) = deal(config, "T♠ 7♥ | 8♦ 8♣ | 8♠ 8♥");
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert!(holds(&game.player_hands()[0], "8♦ 8♠"));
+        assert!(!game.player_choices().contains(PlayerChoices::SPLIT));
+        game.take_turn(PlayerChoice::Stand, &mut balance);
+        assert!(holds(&game.player_hands()[1], "8♣ 8♥"));
+        assert!(!game.player_choices().contains(PlayerChoices::SPLIT));
+
+        let (mut game, mut balance) = deal(GameConfig::default(), "T♠ 7♥ | 8♦ 8♣ | 8♠ 8♥ 8♣");
+        for hands in 2..=4 {
+            game.take_turn(PlayerChoice::Split, &mut balance);
+            assert_eq!(game.player_hands().len(), hands);
+        }
+        // Four hands is the most three splits can make
+        assert!(holds(&game.player_hands()[0], "8♦ 8♣"));
+        assert!(!game.player_choices().contains(PlayerChoices::SPLIT));
+    }
+
+    #[test]
+    fn switching_into_21_is_not_a_blackjack() {
+        let (mut game, _) = deal(GameConfig::blackjack_switch(), "7♠ 7♥ | A♦ 5♣ | 9♠ K♥");
+        game.switch(true);
+        let hand = &game.player_hands()[0];
+        assert!(holds(hand, "A♦ K♥"));
+        assert!(!hand.is_blackjack());
+        // The 21 still has to be stood on like any other hand
+        assert_eq!(game.current_hand, 0);
+    }
+
+    #[test]
+    fn counts_splits_separately_for_each_starting_hand() {
+        let config = GameConfig { player_splits: 1, ..GameConfig::blackjack_switch() };
+        let (mut game, mut balance) = deal(config, "T♠ 7♥ | 8♦ 8♣ | 8♠ 8♥ | 8♣ 3♣ 8♦");
+        game.switch(false);
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert!(holds(&game.player_hands()[0], "8♦ 8♣"));
+        assert!(!game.player_choices().contains(PlayerChoices::SPLIT));
+        game.take_turn(PlayerChoice::Stand, &mut balance);
+        game.take_turn(PlayerChoice::Stand, &mut balance);
+        // The second starting hand still has its own split
+        assert!(holds(&game.player_hands()[2], "8♠ 8♥"));
+        assert!(game.player_choices().contains(PlayerChoices::SPLIT));
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        assert!(holds(&game.player_hands()[2], "8♠ 8♦"));
+        assert!(!game.player_choices().contains(PlayerChoices::SPLIT));
+    }
+
+    #[test]
+    fn split_ace_and_ten_is_21_not_blackjack() {
+        let (mut game, mut balance) = deal(GameConfig::default(), "T♠ 7♥ | A♦ A♣ | K♠ 6♥");
+        game.take_turn(PlayerChoice::Split, &mut balance);
+        let hand = &game.player_hands()[0];
+        assert_eq!(hand.value(), 21);
+        assert!(!hand.is_blackjack());
+        game.play_dealer_hand();
+        assert!(matches!(game.player_wins(0), HandResult::Win));
+        // Even money on the 21 and the soft 17 pushes
+        assert_eq!(game.player_payout(), Money::from_dollars(30));
+    }
+
+    #[test]
+    fn doubling_into_a_charlie_ends_the_hand() {
+        let config = GameConfig {
+            player_charlie: Some(5),
+            player_can_double_any_number_of_cards: true,
+            player_can_rescue: true,
+            ..GameConfig::default()
+        };
+        let (mut game, mut balance) = deal(config, "T♠ 7♥ | 2♦ 2♣ | 2♠ 3♥ 4♦");
+        game.take_turn(PlayerChoice::Hit, &mut balance);
+        game.take_turn(PlayerChoice::Hit, &mut balance);
+        game.take_turn(PlayerChoice::Double, &mut balance);
+        assert!(!game.player_can_play());
+        game.play_dealer_hand();
+        assert!(matches!(game.player_wins(0), HandResult::Win));
+    }
+
+    #[test]
+    fn blackjack_against_blackjack_pushes() {
+        let (game, _) = deal(GameConfig::default(), "A♠ K♥ | A♦ Q♣");
+        assert!(!game.player_can_play());
+        assert!(matches!(game.player_wins(0), HandResult::Push));
+        assert_eq!(game.player_payout(), BET);
+    }
+
+    #[test]
+    fn late_surrender_returns_half_the_bet() {
+        let (mut game, mut balance) = deal(GameConfig::default(), "T♠ 7♥ | T♦ 6♣");
+        assert!(game.player_choices().contains(PlayerChoices::SURRENDER));
+        game.take_turn(PlayerChoice::Surrender, &mut balance);
+        assert!(!game.dealer_needs_to_play());
+        assert!(matches!(game.player_wins(0), HandResult::Surrender));
+        assert_eq!(game.player_payout(), Money::from_dollars(5));
+    }
+
+    #[test]
+    fn counts_decks_by_the_size_of_the_deck_type() {
+        let game = Game::new(GameConfig::spanish_21());
+        assert_eq!(game.cards_left(), Some(6 * 48));
+        assert_eq!(game.decks_left(), Some(6));
+    }
+
+    #[test]
+    #[should_panic(expected = "Stacked shoe ran out of cards")]
+    fn panics_when_the_stack_runs_out() {
+        let (mut game, mut balance) = deal(GameConfig::default(), "T♠ 7♥ | 8♦ 6♣");
+        game.take_turn(PlayerChoice::Hit, &mut balance);
+    }
+}
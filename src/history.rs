@@ -0,0 +1,262 @@
+use std::{fs::OpenOptions, io::{self, Write}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::GameConfig, game::Game, money::Money, shoe::Composition, sidebets::Odds, types::{Card, HandResult, PlayerChoice, PlayerChoices}};
+
+/// Everything that happened in one round, written to the history as a line of JSON
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoundRecord {
+    /// Shoe the round was dealt from, counting from 1 for the session
+    pub shoe: usize,
+    /// Round of the session, counting from 1
+    pub round: usize,
+    /// Round of the shoe, counting from 1
+    pub shoe_round: usize,
+    /// The table rules the round was played under
+    pub config: GameConfig,
+    /// Hi-Lo running and true count when the bet was placed
+    pub running_count: isize,
+    pub true_count: isize,
+    /// Cards left in the shoe when the bet was placed, `None` for an infinite deck
+    pub cards_left: Option<usize>,
+    /// The cards left in the shoe by value when the bet was placed
+    pub composition: Composition,
+    /// The bet on each starting hand
+    pub bet: Money,
+    /// Every card dealt in the order it came out of the shoe
+    pub cards: Vec<Card>,
+    /// The answer to the offer to switch cards between the hands, if it was made
+    pub switched: Option<bool>,
+    /// The answer to the offer to surrender early, if it was made
+    pub early_surrender: Option<bool>,
+    /// Every play the player made, in order
+    pub decisions: Vec<Decision>,
+    /// The dealer's finished hand
+    pub dealer: Vec<Card>,
+    /// Cards the dealer drew after the hands were dealt
+    pub dealer_draws: Vec<Card>,
+    pub hands: Vec<HandRecord>,
+    pub side_bets: Vec<SideBetRecord>,
+    /// Everything the player staked, side bets included
+    pub total_bet: Money,
+    /// Everything handed back to the player, stakes included
+    pub payout: Money,
+}
+
+/// A play made on one of the player's hands
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Decision {
+    /// Hand played, counting from 0 in the order the hands are played
+    pub hand: usize,
+    /// The hand's cards when the play was made
+    pub cards: Vec<Card>,
+    pub true_count: isize,
+    pub choice: PlayerChoice,
+    /// Every play the player could have made
+    pub legal: PlayerChoices,
+}
+
+/// How one of the player's hands finished
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HandRecord {
+    pub cards: Vec<Card>,
+    /// The player's own stake on the hand
+    pub bet: Money,
+    /// The casino's free bet on the hand
+    pub free_bet: Money,
+    pub result: HandResult,
+}
+
+/// How a side bet was settled
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SideBetRecord {
+    pub name: String,
+    pub wager: Money,
+    /// What the bet paid, `None` if it lost
+    pub odds: Option<Odds>,
+    /// Amount handed back to the player, the wager included
+    pub returned: Money,
+}
+
+/// Writes every round of a session as JSON Lines
+#[derive(Serialize, Deserialize)]
+pub struct HandHistory {
+    #[serde(skip, default = "discard")]
+    out: Box<dyn Write>,
+    shoe: usize,
+    round: usize,
+    shoe_round: usize,
+    /// The round being played, from the bet until it's settled
+    current: Option<RoundRecord>,
+}
+
+fn discard() -> Box<dyn Write> {
+    Box::new(io::sink())
+}
+
+impl HandHistory {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        Self {
+            out,
+            shoe: 0,
+            round: 0,
+            shoe_round: 0,
+            current: None,
+        }
+    }
+
+    /// Appends the history to the file at `path`, creating it if needed
+    pub fn append_to(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(Box::new(io::BufWriter::new(file))))
+    }
+
+    /// Carries on numbering the shoes and rounds from a history saved with the session
+    pub fn carry_on_from(&mut self, saved: &HandHistory) {
+        self.shoe = saved.shoe;
+        self.round = saved.round;
+        self.shoe_round = saved.shoe_round;
+    }
+
+    /// Starts a round, called with the counts as they were before the cards are dealt
+    pub fn bet(&mut self, game: &Game, bet: Money) {
+        self.current = Some(RoundRecord {
+            shoe: 0,
+            round: 0,
+            shoe_round: 0,
+            config: game.config.clone(),
+            running_count: game.running_count(),
+            true_count: game.true_count(),
+            cards_left: game.cards_left(),
+            composition: game.shoe_composition(),
+            bet,
+            cards: Vec::new(),
+            switched: None,
+            early_surrender: None,
+            decisions: Vec::new(),
+            dealer: Vec::new(),
+            dealer_draws: Vec::new(),
+            hands: Vec::new(),
+            side_bets: Vec::new(),
+            total_bet: Money::ZERO,
+            payout: Money::ZERO,
+        });
+    }
+
+    pub fn switch(&mut self, switch: bool) {
+        if let Some(record) = self.current.as_mut() {
+            record.switched = Some(switch);
+        }
+    }
+
+    pub fn early_surrender(&mut self, surrender: bool) {
+        if let Some(record) = self.current.as_mut() {
+            record.early_surrender = Some(surrender);
+        }
+    }
+
+    /// Records a play before it's made on the current hand, along with the plays allowed
+    pub fn decision(&mut self, game: &Game, choice: PlayerChoice, legal: PlayerChoices) {
+        let Some(record) = self.current.as_mut() else {
+            return;
+        };
+        let hand = game.current_hand_index();
+        record.decisions.push(Decision {
+            hand,
+            cards: game.player_hands().get(hand).map(|hand| hand.cards.clone()).unwrap_or_default(),
+            true_count: game.true_count(),
+            choice,
+            legal,
+        });
+    }
+
+    /// Fills in how the settled round ended and writes it out
+    pub fn round_over(&mut self, game: &Game) -> io::Result<()> {
+        let Some(mut record) = self.current.take() else {
+            return Ok(());
+        };
+        if game.shuffled_this_round() || self.shoe == 0 {
+            self.shoe += 1;
+            self.shoe_round = 0;
+        }
+        self.round += 1;
+        self.shoe_round += 1;
+        record.shoe = self.shoe;
+        record.round = self.round;
+        record.shoe_round = self.shoe_round;
+
+        record.cards = game.round_cards().to_vec();
+        record.dealer = game.dealer_hand.cards.clone();
+        let dealt = game.config.hole_card_rules.dealer_cards_dealt();
+        record.dealer_draws = record.dealer.iter().skip(dealt).cloned().collect();
+        record.hands = game.player_hands().iter().enumerate()
+            .map(|(i, hand)| HandRecord {
+                cards: hand.cards.clone(),
+                bet: game.player_bets()[i],
+                free_bet: game.free_bets()[i],
+                result: game.player_wins(i),
+            })
+            .collect();
+        record.side_bets = game.side_bets().iter()
+            .map(|bet| SideBetRecord {
+                name: bet.name.clone(),
+                wager: bet.wager,
+                odds: bet.odds,
+                returned: bet.returned(),
+            })
+            .collect();
+        record.total_bet = game.player_total_bet();
+        record.payout = game.player_payout();
+
+        serde_json::to_writer(&mut self.out, &record)?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+}
+
+/// Reads back a history written by [`HandHistory`], one round per line
+pub fn read_history(input: impl io::BufRead) -> io::Result<Vec<RoundRecord>> {
+    input.lines()
+        .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::GameVariant, stages::{GameInPlay, InputNeeded}};
+
+    #[test]
+    fn reads_back_a_round_as_it_was_played() {
+        let path = std::env::temp_dir().join(format!("history-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let game = Game::stacked(GameConfig::free_bet(), "T♠ 7♥ | 5♦ 6♣ | 9♠").unwrap();
+        let mut game = GameInPlay::new(game, Money::from_dollars(100));
+        game.history = Some(HandHistory::append_to(path).unwrap());
+        assert!(matches!(game.advance(), Some(InputNeeded::Bet)));
+        game.bet(Money::from_dollars(10));
+        while game.advance().is_none() {}
+        game.player_move(PlayerChoice::Double);
+        while !matches!(game.advance(), Some(InputNeeded::HandOver)) {}
+        game.history = None;
+
+        let rounds = read_history(io::BufReader::new(std::fs::File::open(path).unwrap())).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let [round] = rounds.as_slice() else { panic!("expected one round, read {}", rounds.len()) };
+        assert_eq!((round.shoe, round.round, round.shoe_round), (1, 1, 1));
+        assert!(matches!(round.config.variant, GameVariant::FreeBet));
+        let dealt: Vec<String> = round.cards.iter().map(Card::to_string).collect();
+        assert_eq!(dealt.join(" "), "10♠ 7♥ 5♦ 6♣ 9♠");
+        assert_eq!(round.decisions.len(), 1);
+        assert!(matches!(round.decisions[0].choice, PlayerChoice::Double));
+        assert!(round.decisions[0].legal.contains(PlayerChoices::DOUBLE));
+        // A free double on the 11 makes 20 against the dealer's 17
+        assert_eq!(round.hands[0].bet, Money::from_dollars(10));
+        assert_eq!(round.hands[0].free_bet, Money::from_dollars(10));
+        assert!(matches!(round.hands[0].result, HandResult::Win));
+        assert_eq!(round.total_bet, Money::from_dollars(10));
+        assert_eq!(round.payout, Money::from_dollars(30));
+    }
+}
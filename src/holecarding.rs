@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameConfig,
+    game::Game,
+    money::Money,
+    shoe::Composition,
+    strategy::{BasicStrategy, BASE_BET},
+    types::{Card, CardFace, Hand, PlayerChoice, PlayerChoices, Suit},
+};
+
+/// What the player learned about the dealer's hole card this round
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum HoleCardTell {
+    /// The player saw the whole card, from a sloppy dealer or a flashed card
+    Seen(Card),
+    /// The player could only tell whether the card is ten valued, from a warped card
+    TenValued(bool),
+}
+
+impl std::fmt::Display for HoleCardTell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HoleCardTell::Seen(card) => write!(f, "{}", card),
+            HoleCardTell::TenValued(true) => write!(f, "a ten"),
+            HoleCardTell::TenValued(false) => write!(f, "not a ten"),
+        }
+    }
+}
+
+/// Chance of each way the dealer's hand can end, 17 to 21, bust, blackjack and a 22 that
+/// pushes
+type DealerOutcome = [f64; 8];
+const BUST: usize = 5;
+const BLACKJACK: usize = 6;
+const PUSH_22: usize = 7;
+
+/// Card values in the order [`Composition`] keeps them, aces as 11
+const VALUES: [u8; 10] = [11, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+
+/// Plays every hand with whatever the player knows about the dealer's hole card
+///
+/// When the hole card is known the dealer's final hand is worked out exactly and every
+/// play is scored against it, drawing from the composition of the shoe. Without a tell the
+/// player falls back to basic strategy, so comparing the two kinds of rounds shows what the
+/// information is worth.
+#[derive(Default, Serialize, Deserialize)]
+pub struct HoleCarder {
+    pub report: HoleCardReport,
+}
+
+impl HoleCarder {
+    /// The tell arrives after the bet, so every round is bet flat
+    pub fn bet(&mut self) -> Money {
+        BASE_BET
+    }
+
+    pub fn choice(&self, game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+        let Some(tell) = game.hole_card_tell() else {
+            if choices.contains(PlayerChoices::SURRENDER) && BasicStrategy::surrender(hand, dealer_card, game.surrendering_early()) {
+                return PlayerChoice::Surrender;
+            }
+            return BasicStrategy::choice(hand, dealer_card, choices);
+        };
+        let composition = game.shoe_composition();
+        // Once the dealer has peeked a card making blackjack can't be there
+        let peeked = game.config.hole_card_rules.dealer_peeks();
+        let Some(mut odds) = player_odds(game, &composition, dealer_card, Some(&tell), peeked) else {
+            return BasicStrategy::choice(hand, dealer_card, choices);
+        };
+        if choices.contains(PlayerChoices::RESCUE) {
+            // Rescuing loses the original bet, standing risks both halves of the doubled bet
+            return if 2.0 * odds.stand(hand) < -1.0 { PlayerChoice::Rescue } else { PlayerChoice::Stand };
+        }
+        odds.best_choice(hand, choices)
+    }
+
+    /// Surrenders before the peek when playing on is expected to lose more than half the bet
+    pub fn early_surrender(&self, game: &Game, hand: &Hand, dealer_card: &Card) -> bool {
+        let Some(tell) = game.hole_card_tell() else {
+            return BasicStrategy::surrender(hand, dealer_card, true);
+        };
+        let composition = game.shoe_composition();
+        let Some(mut odds) = player_odds(game, &composition, dealer_card, Some(&tell), false) else {
+            return BasicStrategy::surrender(hand, dealer_card, true);
+        };
+        let choices = game.player_choices() | PlayerChoices::SURRENDER;
+        matches!(odds.best_choice(hand, choices), PlayerChoice::Surrender)
+    }
+
+    /// Records the result of the round that was just settled
+    pub fn hand_over(&mut self, game: &Game) {
+        let units = game.initial_wager();
+        if units > Money::ZERO {
+            let result = game.main_bet_net() as f64 / units.cents() as f64;
+            self.report.record(game.hole_card_tell().is_some(), result);
+        }
+    }
+}
+
+/// Odds of the player's plays drawing from `composition`, given whatever is known about the
+/// hole card
+///
+/// `None` if the tell rules out every card left.
+pub(crate) fn player_odds<'a>(game: &'a Game, composition: &'a Composition, up_card: &Card, tell: Option<&HoleCardTell>, peeked: bool) -> Option<PlayerOdds<'a>> {
+    let dealer = dealer_odds(tell, up_card, composition, &game.config, peeked)?;
+    Some(PlayerOdds::new(dealer, composition, &game.config))
+}
+
+/// How the dealer's hand ends given the tell, `None` if the tell rules out every card left
+fn dealer_odds(tell: Option<&HoleCardTell>, up_card: &Card, composition: &Composition, config: &GameConfig, peeked: bool) -> Option<DealerOutcome> {
+    let holes = hole_card_odds(tell, up_card, composition, peeked);
+    if holes.is_empty() {
+        return None;
+    }
+    let mut dealer = [0.0; 8];
+    let mut drawn = HashMap::new();
+    for (hole, chance) in holes {
+        let mut dealer_hand = Hand::new(false);
+        dealer_hand.push(up_card.clone());
+        dealer_hand.push(card_with_value(hole));
+        let outcome = dealer_outcome(&dealer_hand, composition, config, &mut drawn);
+        for (total, odds) in dealer.iter_mut().zip(outcome) {
+            *total += chance * odds;
+        }
+    }
+    Some(dealer)
+}
+
+/// Value of the hole card and its chance, given what the tell and the peek gave away
+fn hole_card_odds(tell: Option<&HoleCardTell>, up_card: &Card, composition: &Composition, peeked: bool) -> Vec<(u8, f64)> {
+    let possible: Vec<u8> = match tell {
+        Some(HoleCardTell::Seen(card)) => return vec![(card.face_value(), 1.0)],
+        Some(HoleCardTell::TenValued(true)) => vec![10],
+        Some(HoleCardTell::TenValued(false)) => VALUES.iter().copied().filter(|value| *value != 10).collect(),
+        None => VALUES.to_vec(),
+    };
+    // If the dealer already peeked, a card making blackjack can't be there
+    let possible: Vec<(u8, f64)> = possible.into_iter()
+        .filter(|value| !peeked || up_card.face_value() + value != 21)
+        .map(|value| (value, composition.count(value) as f64))
+        .collect();
+    let total: f64 = possible.iter().map(|(_, weight)| weight).sum();
+    if total == 0.0 {
+        return Vec::new();
+    }
+    possible.into_iter().map(|(value, weight)| (value, weight / total)).collect()
+}
+
+/// How the dealer's hand ends from here, drawing from the shoe with replacement
+///
+/// Past the first two cards only the value and softness matter, so results are kept in
+/// `drawn` by those
+fn dealer_outcome(hand: &Hand, composition: &Composition, config: &GameConfig, drawn: &mut HashMap<(u8, bool), DealerOutcome>) -> DealerOutcome {
+    let mut outcome = [0.0; 8];
+    if hand.is_blackjack() {
+        outcome[BLACKJACK] = 1.0;
+        return outcome;
+    }
+    let key = (hand.value(), hand.is_soft());
+    if let Some(outcome) = drawn.get(&key) {
+        return *outcome;
+    }
+    if hand.is_bust() {
+        let pushes = config.dealer_22_pushes && hand.value() == 22;
+        outcome[if pushes { PUSH_22 } else { BUST }] = 1.0;
+        return outcome;
+    }
+    if !config.dealer_should_hit(hand) {
+        outcome[hand.value() as usize - 17] = 1.0;
+        return outcome;
+    }
+    for value in VALUES {
+        let chance = composition.probability(value);
+        if chance == 0.0 {
+            continue;
+        }
+        let mut next = hand.clone();
+        next.push(card_with_value(value));
+        for (total, odds) in outcome.iter_mut().zip(dealer_outcome(&next, composition, config, drawn)) {
+            *total += chance * odds;
+        }
+    }
+    drawn.insert(key, outcome);
+    outcome
+}
+
+/// Expected results of the player's plays against a known dealer outcome
+pub(crate) struct PlayerOdds<'a> {
+    dealer: DealerOutcome,
+    composition: &'a Composition,
+    config: &'a GameConfig,
+    /// Best result of hitting or standing, by hand value and softness
+    played: HashMap<(u8, bool), f64>,
+}
+
+impl<'a> PlayerOdds<'a> {
+    fn new(dealer: DealerOutcome, composition: &'a Composition, config: &'a GameConfig) -> Self {
+        Self { dealer, composition, config, played: HashMap::new() }
+    }
+
+    pub(crate) fn best_choice(&mut self, hand: &Hand, choices: PlayerChoices) -> PlayerChoice {
+        self.best(hand, choices).0
+    }
+
+    /// Expected result of playing the hand the best way, in units of the wager
+    pub(crate) fn best_result(&mut self, hand: &Hand, choices: PlayerChoices) -> f64 {
+        self.best(hand, choices).1
+    }
+
+    fn best(&mut self, hand: &Hand, choices: PlayerChoices) -> (PlayerChoice, f64) {
+        let mut options = Vec::new();
+        if choices.contains(PlayerChoices::STAND) {
+            options.push((PlayerChoice::Stand, self.stand(hand)));
+        }
+        if choices.contains(PlayerChoices::HIT) {
+            options.push((PlayerChoice::Hit, self.hit(hand)));
+        }
+        if choices.contains(PlayerChoices::DOUBLE) {
+            options.push((PlayerChoice::Double, self.double(hand)));
+        }
+        if choices.contains(PlayerChoices::SPLIT) {
+            options.push((PlayerChoice::Split, self.split(hand)));
+        }
+        if choices.contains(PlayerChoices::SURRENDER) {
+            options.push((PlayerChoice::Surrender, -0.5));
+        }
+        options.into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or_else(|| (PlayerChoice::Stand, self.stand(hand)))
+    }
+
+    fn stand(&self, hand: &Hand) -> f64 {
+        if hand.is_bust() {
+            return -1.0;
+        }
+        let value = hand.value() as usize;
+        let mut result = self.dealer[BUST] - self.dealer[BLACKJACK];
+        for (dealer_value, chance) in (17..=21).zip(self.dealer) {
+            if value > dealer_value {
+                result += chance;
+            } else if value < dealer_value || self.config.dealer_wins_ties {
+                result -= chance;
+            }
+        }
+        result
+    }
+
+    fn hit(&mut self, hand: &Hand) -> f64 {
+        self.after_draw(hand, |odds, next| odds.play_on(next))
+    }
+
+    fn double(&mut self, hand: &Hand) -> f64 {
+        2.0 * self.after_draw(hand, |odds, next| odds.stand(next))
+    }
+
+    /// Plays both hands the same way, split aces get a single card
+    fn split(&mut self, hand: &Hand) -> f64 {
+        let card = hand.cards[0].clone();
+        let mut single = Hand::new(false);
+        single.push(card.clone());
+        let one_card_only = card.face == CardFace::Ace && !self.config.player_can_hit_split_aces;
+        let can_double = self.config.player_can_double_after_split;
+        let per_hand = self.after_draw(&single, |odds, next| {
+            if one_card_only {
+                return odds.stand(next);
+            }
+            let played = odds.play_on(next);
+            if can_double {
+                played.max(2.0 * odds.after_draw(next, |odds, next| odds.stand(next)))
+            } else {
+                played
+            }
+        });
+        2.0 * per_hand
+    }
+
+    /// Best of standing and hitting on
+    fn play_on(&mut self, hand: &Hand) -> f64 {
+        if hand.is_bust() {
+            return -1.0;
+        }
+        let key = (hand.value(), hand.is_soft());
+        if let Some(result) = self.played.get(&key) {
+            return *result;
+        }
+        let stand = self.stand(hand);
+        let result = if hand.value() == 21 { stand } else { stand.max(self.hit(hand)) };
+        self.played.insert(key, result);
+        result
+    }
+
+    fn after_draw(&mut self, hand: &Hand, mut then: impl FnMut(&mut Self, &Hand) -> f64) -> f64 {
+        let mut result = 0.0;
+        for value in VALUES {
+            let chance = self.composition.probability(value);
+            if chance == 0.0 {
+                continue;
+            }
+            let mut next = hand.clone();
+            next.push(card_with_value(value));
+            result += chance * then(self, &next);
+        }
+        result
+    }
+}
+
+/// A card of the given value to work the odds with, the suit doesn't matter
+fn card_with_value(value: u8) -> Card {
+    let face = if value == 11 { CardFace::Ace } else { CardFace::Number(value) };
+    Card { suit: Suit::Spades, face, cut_card: false }
+}
+
+/// Compares the rounds played with a tell against the rounds played blind
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct HoleCardReport {
+    pub informed_rounds: usize,
+    pub informed_result: f64,
+    pub blind_rounds: usize,
+    pub blind_result: f64,
+}
+
+impl HoleCardReport {
+    pub fn record(&mut self, informed: bool, result: f64) {
+        if informed {
+            self.informed_rounds += 1;
+            self.informed_result += result;
+        } else {
+            self.blind_rounds += 1;
+            self.blind_result += result;
+        }
+    }
+
+    /// EV per unit of the rounds played knowing something about the hole card
+    pub fn informed_ev(&self) -> f64 {
+        if self.informed_rounds == 0 {
+            return 0.0;
+        }
+        self.informed_result / self.informed_rounds as f64
+    }
+
+    /// EV per unit of the rounds played with basic strategy
+    pub fn blind_ev(&self) -> f64 {
+        if self.blind_rounds == 0 {
+            return 0.0;
+        }
+        self.blind_result / self.blind_rounds as f64
+    }
+}
+
+impl std::fmt::Display for HoleCardReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "EV with a tell: {:.2}% over {} rounds, without: {:.2}% over {} rounds, tell worth: {:.2}%",
+            self.informed_ev() * 100.0,
+            self.informed_rounds,
+            self.blind_ev() * 100.0,
+            self.blind_rounds,
+            (self.informed_ev() - self.blind_ev()) * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_aces(second: &str) -> Hand {
+        let mut hand = Hand::new(false);
+        hand.push("A♦".parse().unwrap());
+        hand.push(second.parse().unwrap());
+        hand.from_split = true;
+        hand
+    }
+
+    #[test]
+    fn only_picks_plays_the_hand_allows() {
+        let game = Game::new(GameConfig::default());
+        let composition = game.shoe_composition();
+        let up_card: Card = "6♠".parse().unwrap();
+        let mut odds = player_odds(&game, &composition, &up_card, None, true).unwrap();
+        let hand = split_aces("5♣");
+        assert!(matches!(odds.best_choice(&hand, PlayerChoices::HIT | PlayerChoices::STAND), PlayerChoice::Hit));
+        assert!(matches!(odds.best_choice(&hand, PlayerChoices::STAND), PlayerChoice::Stand));
+    }
+}
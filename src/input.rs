@@ -1,8 +1,8 @@
 use std::io::{self, Write};
 
-use crate::types::{PlayerChoice, PlayerChoices};
+use crate::{money::Money, types::{cut_range, PlayerChoice, PlayerChoices}};
 
-pub fn get_player_bet() -> u32 {
+pub fn get_player_bet() -> Money {
     loop {
         print!("Enter your bet (or 0 to quit): $");
         io::stdout().flush().unwrap();
@@ -10,10 +10,9 @@ pub fn get_player_bet() -> u32 {
         let mut input = String::new();
         io::stdin().read_line(&mut input).unwrap();
         
-        match input.trim().parse::<u32>() {
-            Ok(bet) if bet > 0 => return bet,
-            Ok(0) => return 0,
-            _ => {
+        match Money::parse(&input) {
+            Some(bet) => return bet,
+            None => {
                 println!("Please enter a valid bet amount (positive number).");
                 continue;
             }
@@ -21,6 +20,73 @@ pub fn get_player_bet() -> u32 {
     }
 }
 
+/// Asks where to cut the shoe, `None` lets the dealer pick a spot
+pub fn get_player_cut(cards: usize) -> Option<usize> {
+    let range = cut_range(cards);
+    loop {
+        print!(
+            "The shoe has {} cards. Cut between {} and {} cards from the top (Enter for a random cut): ",
+            cards, range.start(), range.end()
+        );
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        match input.parse::<usize>() {
+            Ok(position) if range.contains(&position) => return Some(position),
+            _ => {
+                println!("Please enter a number of cards between {} and {}.", range.start(), range.end());
+                continue;
+            }
+        }
+    }
+}
+
+/// Asks whether to swap the second cards of the two hands
+pub fn get_switch() -> bool {
+    loop {
+        print!("Switch the second cards of your hands? [Y/N]: ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+
+        match input.trim().to_uppercase().as_str() {
+            "Y" | "YES" => return true,
+            "N" | "NO" => return false,
+            _ => {
+                println!("Please answer Y or N.");
+                continue;
+            }
+        }
+    }
+}
+
+/// Asks whether to surrender before the dealer checks for blackjack
+pub fn get_early_surrender() -> bool {
+    loop {
+        print!("Surrender before the dealer checks for blackjack? [Y/N]: ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+
+        match input.trim().to_uppercase().as_str() {
+            "Y" | "YES" => return true,
+            "N" | "NO" => return false,
+            _ => {
+                println!("Please answer Y or N.");
+                continue;
+            }
+        }
+    }
+}
+
 pub fn get_player_choice(choices: PlayerChoices) -> PlayerChoice {
     loop {
         println!();
@@ -32,6 +98,9 @@ pub fn get_player_choice(choices: PlayerChoices) -> PlayerChoice {
         io::stdin().read_line(&mut input).unwrap();
 
         match PlayerChoice::parse_choice(&input, choices) {
+            Ok(PlayerChoice::DoubleForLess(_)) => {
+                return PlayerChoice::DoubleForLess(get_double_amount());
+            }
             Ok(choice) => {
                 return choice;
             }
@@ -43,6 +112,25 @@ pub fn get_player_choice(choices: PlayerChoices) -> PlayerChoice {
     }
 }
 
+/// Asks how much to double for, the game limits it to the original bet
+fn get_double_amount() -> Money {
+    loop {
+        print!("Double for how much: $");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+
+        match Money::parse(&input) {
+            Some(amount) if amount > Money::ZERO => return amount,
+            _ => {
+                println!("Please enter an amount greater than zero.");
+                continue;
+            }
+        }
+    }
+}
+
 pub fn wait_for_player_input(sleep: bool) {
     if sleep {
         std::thread::sleep(std::time::Duration::from_secs(1));
@@ -70,4 +158,10 @@ fn print_player_choices(choices: PlayerChoices) {
     if choices.contains(PlayerChoices::SURRENDER) {
         println!("  [R] [5] Surrender");
     }
+    if choices.contains(PlayerChoices::DOUBLE_FOR_LESS) {
+        println!("  [L] [6] Double for Less");
+    }
+    if choices.contains(PlayerChoices::RESCUE) {
+        println!("  [E] [7] Rescue");
+    }
 }
\ No newline at end of file
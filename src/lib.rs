@@ -1,7 +1,21 @@
 pub mod types;
 pub mod game;
+pub mod shoe;
+pub mod shuffle;
 pub mod config;
 pub mod error;
+pub mod doubleexposure;
+pub mod freebet;
+pub mod history;
+pub mod holecarding;
 pub mod input;
+pub mod money;
+pub mod replay;
+pub mod sequencing;
+pub mod sidebets;
+pub mod spanish21;
 pub mod stages;
-pub mod strategy;
\ No newline at end of file
+pub mod stats;
+pub mod strategy;
+pub mod switch;
+pub mod tracking;
\ No newline at end of file
@@ -1,6 +1,9 @@
 use blackjack_sim::{
     config::GameConfig,
     game::Game,
+    history::{read_history, HandHistory},
+    money::Money,
+    replay::{ReplaySummary, RoundReplay},
     stages::{GameInPlay, InputNeeded}, strategy::PlayMode,
 };
 
@@ -8,39 +11,86 @@ fn main() {
     clear_screen();
     let args: Vec<String> = std::env::args().collect();
 
-    let play_mode = PlayMode::from_args(&args);
+    let mut play_mode = PlayMode::from_args(&args);
 
-    let config = GameConfig::default();
-    let game = Game::new(config);
-    let balance = 10000;
-
-    let mut game = GameInPlay::new(game, balance);
-    let mut hands_played = 0;
+    let config = GameConfig::from_args(&args);
+    if let Some(path) = args.iter().position(|arg| arg == "--replay").and_then(|index| args.get(index + 1)) {
+        replay(path, config, &play_mode);
+        return;
+    }
+    let save = args.iter().position(|arg| arg == "--save").and_then(|index| args.get(index + 1));
+    // A saved session keeps the table it was played at
+    let mut game = match save.filter(|path| std::path::Path::new(path).exists()) {
+        Some(path) => match GameInPlay::load(path) {
+            Ok((game, saved_mode)) => {
+                play_mode = saved_mode;
+                game
+            }
+            Err(error) => {
+                eprintln!("Can't resume the session saved in {}: {}", path, error);
+                return;
+            }
+        },
+        None => GameInPlay::new(Game::new(config), Money::from_dollars(10000)),
+    };
+    // The saved history only knows how far the numbering got, the rounds are already in the file
+    let saved_history = game.history.take();
+    if let Some(path) = args.iter().position(|arg| arg == "--history").and_then(|index| args.get(index + 1)) {
+        match HandHistory::append_to(path) {
+            Ok(mut history) => {
+                if let Some(saved) = &saved_history {
+                    history.carry_on_from(saved);
+                }
+                game.history = Some(history);
+            }
+            Err(error) => eprintln!("Can't write the hand history to {}: {}", path, error),
+        }
+    }
+    let mut hands_played = game.stats.rounds;
 
     loop {
         match game.advance() {
+            Some(InputNeeded::Cut) => {
+                println!("{}", game);
+                println!("Shuffling a new shoe...");
+                let position = play_mode.cut(&game.game);
+                game.cut(position);
+            }
             Some(InputNeeded::Bet) => {
+                if let Some(Err(error)) = save.map(|path| game.save(path, &play_mode)) {
+                    eprintln!("Can't save the session: {}", error);
+                }
                 println!("{}", game);
                 println!("Hands played: {}", hands_played);
-                let mut bet = play_mode.bet(game.balance, game.game.true_count());
+                let mut bet = play_mode.bet(game.balance, &game.game);
                 println!("{}", game);
                 println!("Hands played: {}", hands_played);
-                if bet == 0 {
+                if bet == Money::ZERO {
                     println!("Going to new table...");
                     game.new_table();
-                    bet = play_mode.bet(game.balance, game.game.true_count());
+                    bet = play_mode.bet(game.balance, &game.game);
                 }
                 game.bet(bet);
                 println!("{}", game);
             }
+            Some(InputNeeded::Switch) => {
+                println!("{}", game);
+                let switch = play_mode.switch(&game.game);
+                game.switch(switch);
+            }
+            Some(InputNeeded::EarlySurrender) => {
+                println!("{}", game);
+                let surrender = play_mode.early_surrender(&game.game);
+                game.early_surrender(surrender);
+            }
             Some(InputNeeded::Choice) => {
                 println!("{}", game);
                 println!("Hands played: {}", hands_played);
-                let choices = game.game.player_choices();
+                let choices = game.player_choices();
                 let hand = game.game.player_current_hand();
                 let dealer_card = game.game.dealer_up_card();
                 let true_count = game.game.true_count();
-                let choice = play_mode.choice(choices, &hand, &dealer_card, true_count);
+                let choice = play_mode.choice(choices, &hand, &dealer_card, true_count, &game.game);
                 
                 game.player_move(choice);
             }
@@ -48,6 +98,11 @@ fn main() {
                 hands_played += 1;
                 println!("{}", game);
                 println!("Hands played: {}", hands_played);
+                println!("{}", game.stats);
+                play_mode.hand_over(&game.game);
+                if let Some(report) = play_mode.report() {
+                    println!("{}", report);
+                }
                 play_mode.wait_for_player_input();
             }
             None => {
@@ -60,6 +115,57 @@ fn main() {
     }
 }
 
+/// Steps through every round of a hand history, reviewing each decision against the
+/// basic and counting strategies
+fn replay(path: &str, config: GameConfig, play_mode: &PlayMode) {
+    let rounds = match std::fs::File::open(path).and_then(|file| read_history(std::io::BufReader::new(file))) {
+        Ok(rounds) => rounds,
+        Err(error) => {
+            eprintln!("Can't read the hand history from {}: {}", path, error);
+            return;
+        }
+    };
+    let mut summary = ReplaySummary::default();
+    let mut mistakes = Vec::new();
+    for record in rounds {
+        let mut replay = match RoundReplay::new(config.clone(), record) {
+            Ok(replay) => replay,
+            Err(error) => {
+                eprintln!("{}", error);
+                return;
+            }
+        };
+        loop {
+            match replay.step() {
+                Ok(Some(step)) => {
+                    clear_screen();
+                    let record = &replay.record;
+                    println!(
+                        "Shoe {}, round {} ({} of the shoe), running count {}, true count {}",
+                        record.shoe, record.round, record.shoe_round, record.running_count, record.true_count
+                    );
+                    println!("{}", replay.game);
+                    println!("{}", step);
+                    play_mode.wait_for_player_input();
+                }
+                Ok(None) => {
+                    summary.record(&replay);
+                    mistakes.extend(replay.mistakes().map(|mistake| format!("Round {}: {}", replay.record.round, mistake)));
+                    break;
+                }
+                Err(error) => {
+                    summary.record_divergence(&error);
+                    break;
+                }
+            }
+        }
+    }
+    clear_screen();
+    for mistake in &mistakes {
+        println!("{}", mistake);
+    }
+    println!("{}", summary);
+}
 
 fn clear_screen() {
     // This function clears the console screen.
@@ -0,0 +1,173 @@
+use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+/// An amount of money, kept in cents so a 3:2 payout on $5 comes to exactly $7.50
+///
+/// Written out as a whole number of cents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Money(u64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_dollars(dollars: u64) -> Self {
+        Money(dollars * 100)
+    }
+
+    pub const fn from_cents(cents: u64) -> Self {
+        Money(cents)
+    }
+
+    pub fn cents(self) -> u64 {
+        self.0
+    }
+
+    pub fn dollars(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    /// `win` for every `per` of this amount, rounded down to the cent the way casinos pay
+    pub fn times(self, win: u32, per: u32) -> Money {
+        Money(self.0 * win as u64 / per as u64)
+    }
+
+    /// A fraction of this amount rounded down to the cent, for sizing bets off a bankroll
+    pub fn fraction(self, fraction: f64) -> Money {
+        Money((self.0 as f64 * fraction.max(0.0)) as u64)
+    }
+
+    pub fn saturating_sub(self, other: Money) -> Money {
+        Money(self.0.saturating_sub(other.0))
+    }
+
+    /// The signed difference `self - other` in cents
+    pub fn net_of(self, other: Money) -> i64 {
+        self.0 as i64 - other.0 as i64
+    }
+
+    /// Reads an amount in dollars such as `25`, `7.5` or `$7.50`
+    pub fn parse(input: &str) -> Option<Money> {
+        let input = input.trim().trim_start_matches('$');
+        if input.is_empty() {
+            return None;
+        }
+        let (dollars, cents) = match input.split_once('.') {
+            Some((dollars, cents)) => (dollars, cents),
+            None => (input, ""),
+        };
+        if cents.len() > 2 || !cents.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let dollars: u64 = if dollars.is_empty() { 0 } else { dollars.parse().ok()? };
+        let cents: u64 = format!("{:0<2}", cents).parse().ok()?;
+        Some(Money(dollars * 100 + cents))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Mul<u32> for Money {
+    type Output = Money;
+    fn mul(self, times: u32) -> Money {
+        Money(self.0 * times as u64)
+    }
+}
+
+impl std::iter::Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+/// Whole dollars are shown without cents
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_multiple_of(100) {
+            write!(f, "${}", self.0 / 100)
+        } else {
+            write!(f, "${}.{:02}", self.0 / 100, self.0 % 100)
+        }
+    }
+}
+
+/// A net win or loss in cents, shown like [`Money`] with a sign for losses
+pub struct Net(pub i64);
+
+impl std::fmt::Display for Net {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 < 0 {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", Money(self.0.unsigned_abs()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pays_three_to_two_on_five_dollars_to_the_cent() {
+        assert_eq!(Money::from_dollars(5).times(3, 2), Money::from_cents(750));
+        // Odd cents round down the way casinos pay
+        assert_eq!(Money::from_cents(125).times(3, 2), Money::from_cents(187));
+    }
+
+    #[test]
+    fn parses_dollars_and_cents() {
+        assert_eq!(Money::parse("25"), Some(Money::from_dollars(25)));
+        assert_eq!(Money::parse("7.5"), Some(Money::from_cents(750)));
+        assert_eq!(Money::parse(" $7.50 "), Some(Money::from_cents(750)));
+        assert_eq!(Money::parse(".25"), Some(Money::from_cents(25)));
+    }
+
+    #[test]
+    fn rejects_amounts_that_are_not_dollars_and_cents() {
+        assert_eq!(Money::parse(""), None);
+        assert_eq!(Money::parse("$"), None);
+        assert_eq!(Money::parse("7.505"), None);
+        assert_eq!(Money::parse("-5"), None);
+        assert_eq!(Money::parse("five"), None);
+        assert_eq!(Money::parse("7.5x"), None);
+    }
+
+    #[test]
+    fn shows_cents_only_when_there_are_some() {
+        assert_eq!(Money::from_dollars(10).to_string(), "$10");
+        assert_eq!(Money::from_cents(750).to_string(), "$7.50");
+        assert_eq!(Money::from_cents(5).to_string(), "$0.05");
+        assert_eq!(Net(-750).to_string(), "-$7.50");
+    }
+}
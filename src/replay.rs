@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GameConfig,
+    error::Error,
+    game::Game,
+    history::{Decision, RoundRecord},
+    shoe::{Composition, Shoe},
+    stages::{GameInPlay, InputNeeded},
+    strategy::{basic_choice, counting_choice},
+    types::{Card, Deck, PlayerChoice, PlayerChoices},
+};
+
+/// A recorded play next to what the basic and counting strategies would have made
+#[derive(Clone, Debug)]
+pub struct ReviewedDecision {
+    pub decision: Decision,
+    pub dealer_card: Card,
+    pub basic: PlayerChoice,
+    /// The counting strategy's play at the true count recorded with the decision
+    pub counting: PlayerChoice,
+}
+
+impl ReviewedDecision {
+    /// True when neither strategy makes the play, doubling for less counts as a double
+    pub fn is_mistake(&self) -> bool {
+        let play = |choice: PlayerChoice| match choice {
+            PlayerChoice::DoubleForLess(_) => PlayerChoices::DOUBLE,
+            choice => choice.into(),
+        };
+        let played = play(self.decision.choice);
+        played != play(self.basic) && played != play(self.counting)
+    }
+}
+
+impl std::fmt::Display for ReviewedDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cards: Vec<String> = self.decision.cards.iter().map(Card::to_string).collect();
+        write!(
+            f,
+            "Hand {} [{}] against {} at true count {}: {}, basic strategy {}, counting {}",
+            self.decision.hand + 1,
+            cards.join(", "),
+            self.dealer_card,
+            self.decision.true_count,
+            self.decision.choice,
+            self.basic,
+            self.counting
+        )?;
+        if self.is_mistake() {
+            write!(f, " <- MISTAKE")?;
+        }
+        Ok(())
+    }
+}
+
+/// What happened in one step of a replayed round
+pub enum ReplayStep {
+    /// The bet was placed and the cards dealt
+    Dealt,
+    Switch(bool),
+    EarlySurrender(bool),
+    Decision(ReviewedDecision),
+    /// The dealer revealed or played the hand
+    Dealer,
+    /// The round was settled and paid what the history says
+    Settled,
+}
+
+/// Plays a recorded round again through [`GameInPlay`] with the same cards and decisions
+///
+/// The round is dealt in the recorded order from a shoe that looks the way it did when the
+/// bet was placed, with the same count and cards left, so it has to be replayed with the
+/// rules it was played with. Rounds recorded under other rules are refused.
+pub struct RoundReplay {
+    pub game: GameInPlay,
+    pub record: RoundRecord,
+    /// Index of the next recorded decision
+    next_decision: usize,
+    /// Every play made so far, reviewed
+    pub reviewed: Vec<ReviewedDecision>,
+    finished: bool,
+}
+
+impl RoundReplay {
+    pub fn new(config: GameConfig, record: RoundRecord) -> Result<Self, Error> {
+        let rules = |config: &GameConfig| serde_json::to_value(config).ok();
+        if rules(&config) != rules(&record.config) {
+            return Err(Error::ReplayRulesDiffer(record.round));
+        }
+        let shoe = ReplayShoe::new(&record, &config);
+        let game = Game::with_shoe(config, Box::new(shoe));
+        Ok(Self {
+            // Exactly what was staked, so doubles aren't turned into doubles for less
+            game: GameInPlay::new(game, record.total_bet),
+            record,
+            next_decision: 0,
+            reviewed: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Takes the round one step further, `None` once it has been settled
+    pub fn step(&mut self) -> Result<Option<ReplayStep>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        loop {
+            let step = match self.game.advance() {
+                Some(InputNeeded::Cut) => {
+                    self.game.cut(None);
+                    continue;
+                }
+                Some(InputNeeded::Bet) => {
+                    self.game.bet(self.record.bet);
+                    ReplayStep::Dealt
+                }
+                Some(InputNeeded::Switch) => {
+                    let switch = self.record.switched.unwrap_or(false);
+                    self.game.switch(switch);
+                    ReplayStep::Switch(switch)
+                }
+                Some(InputNeeded::EarlySurrender) => {
+                    let surrender = self.record.early_surrender.unwrap_or(false);
+                    self.game.early_surrender(surrender);
+                    ReplayStep::EarlySurrender(surrender)
+                }
+                Some(InputNeeded::Choice) => ReplayStep::Decision(self.replay_decision()?),
+                Some(InputNeeded::HandOver) => {
+                    self.check_settled()?;
+                    self.finished = true;
+                    ReplayStep::Settled
+                }
+                None => ReplayStep::Dealer,
+            };
+            return Ok(Some(step));
+        }
+    }
+
+    /// Plays the round through to the end
+    pub fn finish(&mut self) -> Result<(), Error> {
+        while self.step()?.is_some() {}
+        Ok(())
+    }
+
+    pub fn mistakes(&self) -> impl Iterator<Item = &ReviewedDecision> {
+        self.reviewed.iter().filter(|reviewed| reviewed.is_mistake())
+    }
+
+    fn replay_decision(&mut self) -> Result<ReviewedDecision, Error> {
+        let hand = self.game.game.player_current_hand();
+        let choices = self.game.player_choices();
+        let game = &self.game.game;
+        let Some(decision) = self.record.decisions.get(self.next_decision).cloned() else {
+            return Err(self.diverged("the player has a hand to play after the last recorded decision"));
+        };
+        if decision.hand != game.current_hand_index() || !same_cards(&decision.cards, &hand.cards) {
+            return Err(self.diverged("the hand being played doesn't match the recorded decision"));
+        }
+        if !choices.contains(decision.choice.into()) {
+            return Err(self.diverged(&format!("{} isn't allowed here", decision.choice)));
+        }
+        let dealer_card = game.dealer_up_card();
+        // The replay only holds what was staked, so the plays are judged against what the
+        // player could afford at the time
+        let legal = decision.legal;
+        let reviewed = ReviewedDecision {
+            basic: basic_choice(game, &hand, &dealer_card, legal),
+            counting: counting_choice(game, &hand, &dealer_card, legal, decision.true_count),
+            decision,
+            dealer_card,
+        };
+        self.next_decision += 1;
+        self.game.player_move(reviewed.decision.choice);
+        self.reviewed.push(reviewed.clone());
+        Ok(reviewed)
+    }
+
+    fn check_settled(&self) -> Result<(), Error> {
+        let game = &self.game.game;
+        if self.next_decision < self.record.decisions.len() {
+            return Err(self.diverged("the round ended before every recorded decision was made"));
+        }
+        if !same_cards(game.round_cards(), &self.record.cards) {
+            return Err(self.diverged("different cards were dealt"));
+        }
+        if game.player_payout() != self.record.payout {
+            return Err(self.diverged(&format!("paid {} instead of {}", game.player_payout(), self.record.payout)));
+        }
+        Ok(())
+    }
+
+    fn diverged(&self, reason: &str) -> Error {
+        Error::ReplayDiverged(format!("round {}, {}", self.record.round, reason))
+    }
+}
+
+impl std::fmt::Display for ReplayStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayStep::Dealt => write!(f, "Cards dealt"),
+            ReplayStep::Switch(true) => write!(f, "Switched the second cards"),
+            ReplayStep::Switch(false) => write!(f, "Kept the cards as dealt"),
+            ReplayStep::EarlySurrender(true) => write!(f, "Surrendered early"),
+            ReplayStep::EarlySurrender(false) => write!(f, "Turned down early surrender"),
+            ReplayStep::Decision(reviewed) => write!(f, "{}", reviewed),
+            ReplayStep::Dealer => write!(f, "Dealer's turn"),
+            ReplayStep::Settled => write!(f, "Round settled as recorded"),
+        }
+    }
+}
+
+/// Tallies the decisions and mistakes over every replayed round
+#[derive(Default)]
+pub struct ReplaySummary {
+    pub rounds: usize,
+    pub decisions: usize,
+    pub mistakes: usize,
+    /// Rounds that couldn't be replayed and why
+    pub diverged: Vec<String>,
+}
+
+impl ReplaySummary {
+    pub fn record(&mut self, replay: &RoundReplay) {
+        self.rounds += 1;
+        self.decisions += replay.reviewed.len();
+        self.mistakes += replay.mistakes().count();
+    }
+
+    pub fn record_divergence(&mut self, error: &Error) {
+        self.diverged.push(error.to_string());
+    }
+}
+
+impl std::fmt::Display for ReplaySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Rounds replayed: {}, Decisions: {}, Mistakes: {}",
+            self.rounds, self.decisions, self.mistakes
+        )?;
+        for reason in &self.diverged {
+            write!(f, "\n{}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deals a recorded round in order while keeping the count and composition of the shoe it
+/// was dealt from
+#[derive(Serialize, Deserialize)]
+struct ReplayShoe {
+    /// Cards left to deal, the next card is at the end
+    cards: Vec<Card>,
+    cards_left: Option<usize>,
+    composition: Composition,
+    running_count: isize,
+}
+
+impl ReplayShoe {
+    fn new(record: &RoundRecord, config: &GameConfig) -> Self {
+        // Spare cards after the round show up as a mismatch instead of an empty shoe
+        let mut cards = record.cards.clone();
+        cards.extend(Deck::of_type(&config.deck_type).cards);
+        cards.reverse();
+        Self {
+            cards,
+            cards_left: record.cards_left,
+            composition: record.composition.clone(),
+            running_count: record.running_count,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Shoe for ReplayShoe {
+    fn draw(&mut self) -> Card {
+        let card = self.cards.pop().expect("Replay shoe ran out of cards");
+        self.running_count += card.count();
+        // An infinite deck looks the same after every card
+        if let Some(cards_left) = self.cards_left.as_mut() {
+            *cards_left = cards_left.saturating_sub(1);
+            self.composition.remove(&card);
+        }
+        card
+    }
+    fn cards_remaining(&self) -> Option<usize> {
+        self.cards_left
+    }
+    fn composition(&self) -> Composition {
+        self.composition.clone()
+    }
+    fn needs_reshuffle(&self) -> bool {
+        false
+    }
+    fn reshuffle(&mut self) {}
+    fn running_count(&self) -> isize {
+        self.running_count
+    }
+}
+
+fn same_cards(first: &[Card], second: &[Card]) -> bool {
+    first.len() == second.len() && first.iter().zip(second).all(|(a, b)| a.is_same_card(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::{read_history, HandHistory}, money::Money};
+
+    /// Plays a round from the stacked cards with the given plays and reads back its record,
+    /// written to a history file `name`d for the test
+    fn record(name: &str, config: GameConfig, notation: &str, plays: &[PlayerChoice]) -> RoundRecord {
+        let path = std::env::temp_dir().join(format!("{}-{}.jsonl", name, std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut game = GameInPlay::new(Game::stacked(config, notation).unwrap(), Money::from_dollars(100));
+        game.history = Some(HandHistory::append_to(path).unwrap());
+        assert!(matches!(game.advance(), Some(InputNeeded::Bet)));
+        game.bet(Money::from_dollars(10));
+        for &play in plays {
+            while game.advance().is_none() {}
+            game.player_move(play);
+        }
+        while !matches!(game.advance(), Some(InputNeeded::HandOver)) {}
+        game.history = None;
+        let rounds = read_history(std::io::BufReader::new(std::fs::File::open(path).unwrap())).unwrap();
+        std::fs::remove_file(path).unwrap();
+        rounds.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn flags_plays_neither_strategy_makes() {
+        // Hitting 11 against a ten instead of doubling, then rightly hitting the 13
+        let plays = [PlayerChoice::Hit, PlayerChoice::Hit];
+        let round = record("flags-mistakes", GameConfig::default(), "T♠ 7♥ | 5♦ 6♣ | 2♠ 9♥", &plays);
+        let mut replay = RoundReplay::new(GameConfig::default(), round).unwrap();
+        replay.finish().unwrap();
+        let [hit_11, hit_13] = replay.reviewed.as_slice() else { panic!("expected two decisions") };
+        assert!(matches!(hit_11.basic, PlayerChoice::Double));
+        assert!(hit_11.is_mistake());
+        assert!(matches!(hit_13.basic, PlayerChoice::Hit));
+        assert!(!hit_13.is_mistake());
+        assert_eq!(replay.mistakes().count(), 1);
+    }
+
+    #[test]
+    fn refuses_rounds_played_under_other_rules() {
+        let round = record("refuses-rules", GameConfig::default(), "T♠ 7♥ | T♦ 9♣", &[PlayerChoice::Stand]);
+        assert!(matches!(RoundReplay::new(GameConfig::free_bet(), round), Err(Error::ReplayRulesDiffer(1))));
+    }
+}
@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{game::Game, money::Money, shoe::Composition, strategy::BASE_BET, tracking::{best_cut, expected_through_shuffle}, types::{Card, CardFace, Deck}};
+
+/// Follows aces through the shuffle using the cards picked up with them
+///
+/// When a shoe is rebuilt from its discards the card that sat on top of an ace in the tray
+/// tends to stay just above it through the riffles, so it comes out of the next shoe right
+/// before the ace. The sequencer notes those key cards and, once one is dealt, expects an
+/// ace within the next few cards and bets up while the prediction is live.
+#[derive(Serialize, Deserialize)]
+pub struct AceSequencer {
+    /// Number of cards after a key card the ace is expected within
+    pub window: usize,
+    /// Bet units while an ace is expected in the coming round
+    pub ace_units: u32,
+    /// Number of practice shuffles used to learn where the aces end up when cutting
+    pub simulations: usize,
+    /// Key cards noted from the tray the current shoe was shuffled from
+    keys: Vec<Card>,
+    /// The shoe the key cards were noted for
+    shoe: Option<usize>,
+    /// Cards dealt since the last key card, while an ace prediction is live
+    since_key: Option<usize>,
+    /// Whether the bet for the current round was raised for an ace
+    betting_on_ace: bool,
+    /// Set when the shoe was cut to bring an ace to the player's first hand
+    ace_cut: bool,
+    /// Whether the current round is the first one after an ace cut
+    cut_round: bool,
+
+    pub report: SequencingReport,
+}
+
+impl Default for AceSequencer {
+    fn default() -> Self {
+        Self {
+            window: 4,
+            ace_units: 6,
+            simulations: 100,
+            keys: Vec::new(),
+            shoe: None,
+            since_key: None,
+            betting_on_ace: false,
+            ace_cut: false,
+            cut_round: false,
+            report: SequencingReport::default(),
+        }
+    }
+}
+
+impl AceSequencer {
+    pub fn bet(&mut self, game: &Game) -> Money {
+        self.note_key_cards(game);
+        self.cut_round = std::mem::take(&mut self.ace_cut);
+        // The dealer takes the first cards, so the ace has to come after them
+        let dealer_cards = game.config.hole_card_rules.dealer_cards_dealt();
+        self.betting_on_ace = self.cut_round || self.since_key.is_some_and(|since| since + dealer_cards < self.window);
+        if self.betting_on_ace {
+            BASE_BET * self.ace_units
+        } else {
+            BASE_BET
+        }
+    }
+
+    /// Watches the cards dealt in the round that was just settled
+    pub fn hand_over(&mut self, game: &Game) {
+        if self.cut_round {
+            let dealer_cards = game.config.hole_card_rules.dealer_cards_dealt();
+            let hit = game.round_cards().iter().skip(dealer_cards).take(2).any(|card| card.face == CardFace::Ace);
+            self.report.record_cut(hit);
+            self.cut_round = false;
+        }
+        for card in game.round_cards() {
+            self.report.record_card(card);
+            self.watch(card);
+        }
+        let units = game.initial_wager();
+        if units > Money::ZERO {
+            let result = game.main_bet_net() as f64 / units.cents() as f64;
+            self.report.record_round(self.betting_on_ace, result);
+        }
+        self.betting_on_ace = false;
+    }
+
+    /// Ace cutting: cuts the shoe so the aces from the tray most likely to be together land
+    /// in the player's first hand, which comes right after the dealer's cards
+    pub fn cut(&mut self, game: &Game) -> Option<usize> {
+        self.note_key_cards(game);
+        let record = game.last_shuffle()?;
+        let ace_share = Composition::from_cards(&Deck::of_type(&game.config.deck_type).cards).probability(1);
+        let aces_seen = record.discards.iter().filter(|card| card.face == CardFace::Ace).count();
+        let shoe_aces = (record.len() as f64 * ace_share).round() as usize;
+        let unseen_aces = shoe_aces.saturating_sub(aces_seen);
+        let unseen_ace = if record.unseen > 0 { unseen_aces as f64 / record.unseen as f64 } else { 0.0 };
+        let aces: Vec<f64> = record.discards.iter()
+            .map(|card| if card.face == CardFace::Ace { 1.0 } else { 0.0 })
+            .chain(std::iter::repeat_n(unseen_ace, record.unseen))
+            .collect();
+
+        let map = expected_through_shuffle(&aces, &game.config.shuffle_routine, self.simulations);
+        // Without a hole card the player's cards come right after the dealer's up card
+        let dealer_cards = game.config.hole_card_rules.dealer_cards_dealt();
+        let dealt = dealer_cards + 2;
+        let player_aces = |window: &[f64]| window[dealer_cards] + window[dealer_cards + 1];
+        let position = best_cut(&map, dealt, player_aces)?;
+        // The best of many cuts always looks good on the shuffles it was picked from, so
+        // check it on a fresh set before betting on it
+        let check = expected_through_shuffle(&aces, &game.config.shuffle_routine, self.simulations);
+        let expected = player_aces(&check[position..position + dealt]);
+        self.ace_cut = expected > 1.5 * 2.0 * ace_share;
+        Some(position)
+    }
+
+    /// Whether an ace is expected within the next few cards
+    pub fn ace_expected(&self) -> bool {
+        self.since_key.is_some()
+    }
+
+    /// Key cards of the current shoe
+    pub fn key_cards(&self) -> &[Card] {
+        &self.keys
+    }
+
+    fn note_key_cards(&mut self, game: &Game) {
+        let Some(record) = game.last_shuffle() else {
+            return;
+        };
+        if self.shoe == Some(record.shoe) {
+            return;
+        }
+        self.shoe = Some(record.shoe);
+        self.since_key = None;
+        // The card discarded right after an ace lands on top of it in the pile
+        self.keys = record.discards.windows(2)
+            .filter(|pair| pair[0].face == CardFace::Ace && pair[1].face != CardFace::Ace)
+            .map(|pair| pair[1].clone())
+            .collect();
+    }
+
+    fn watch(&mut self, card: &Card) {
+        if let Some(since) = self.since_key {
+            if card.face == CardFace::Ace {
+                self.report.record_prediction(true);
+                self.since_key = None;
+                return;
+            }
+            if since + 1 >= self.window {
+                self.report.record_prediction(false);
+                self.since_key = None;
+            } else {
+                self.since_key = Some(since + 1);
+            }
+        }
+        if self.since_key.is_none() && self.keys.iter().any(|key| key.is_same_card(card)) {
+            self.since_key = Some(0);
+        }
+    }
+}
+
+/// How often key cards led to an ace and what betting on them earned
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct SequencingReport {
+    pub predictions: usize,
+    pub hits: usize,
+    /// Rounds and result in units of the initial bet while betting on an ace
+    pub ace_rounds: usize,
+    pub ace_result: f64,
+    /// First rounds after cutting an ace to the player, and how often one arrived
+    pub cut_rounds: usize,
+    pub cut_hits: usize,
+    /// Rounds and result in units of the initial bet otherwise
+    pub other_rounds: usize,
+    pub other_result: f64,
+    /// Cards watched and the aces among them, for the chance of an ace turning up anyway
+    #[serde(default)]
+    pub cards_seen: usize,
+    #[serde(default)]
+    pub aces_seen: usize,
+}
+
+impl SequencingReport {
+    pub fn record_prediction(&mut self, hit: bool) {
+        self.predictions += 1;
+        if hit {
+            self.hits += 1;
+        }
+    }
+
+    pub fn record_cut(&mut self, hit: bool) {
+        self.cut_rounds += 1;
+        if hit {
+            self.cut_hits += 1;
+        }
+    }
+
+    pub fn record_card(&mut self, card: &Card) {
+        self.cards_seen += 1;
+        if card.face == CardFace::Ace {
+            self.aces_seen += 1;
+        }
+    }
+
+    pub fn record_round(&mut self, betting_on_ace: bool, result: f64) {
+        if betting_on_ace {
+            self.ace_rounds += 1;
+            self.ace_result += result;
+        } else {
+            self.other_rounds += 1;
+            self.other_result += result;
+        }
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.predictions == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.predictions as f64
+    }
+
+    /// Chance of an ace within the same number of random cards, going by the share of aces
+    /// in the cards dealt so far
+    pub fn chance_rate(&self, window: usize) -> f64 {
+        if self.cards_seen == 0 {
+            return 0.0;
+        }
+        let ace_share = self.aces_seen as f64 / self.cards_seen as f64;
+        1.0 - (1.0 - ace_share).powi(window as i32)
+    }
+
+    /// EV per unit of the rounds bet on a predicted ace
+    pub fn ace_ev(&self) -> f64 {
+        if self.ace_rounds == 0 {
+            return 0.0;
+        }
+        self.ace_result / self.ace_rounds as f64
+    }
+
+    /// EV per unit of every other round
+    pub fn other_ev(&self) -> f64 {
+        if self.other_rounds == 0 {
+            return 0.0;
+        }
+        self.other_result / self.other_rounds as f64
+    }
+}
+
+impl std::fmt::Display for AceSequencer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let report = &self.report;
+        writeln!(
+            f,
+            "Ace predictions: {}, Hits: {:.1}% (chance {:.1}%)",
+            report.predictions,
+            report.hit_rate() * 100.0,
+            report.chance_rate(self.window) * 100.0
+        )?;
+        if report.cut_rounds > 0 {
+            writeln!(
+                f,
+                "Ace cuts: {}, Ace in first hand: {:.1}% (chance {:.1}%)",
+                report.cut_rounds,
+                report.cut_hits as f64 / report.cut_rounds as f64 * 100.0,
+                report.chance_rate(2) * 100.0
+            )?;
+        }
+        write!(
+            f,
+            "EV betting on an ace: {:.2}% over {} rounds, otherwise: {:.2}% over {} rounds",
+            report.ace_ev() * 100.0,
+            report.ace_rounds,
+            report.other_ev() * 100.0,
+            report.other_rounds
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeckType;
+
+    #[test]
+    fn chance_of_an_ace_follows_the_cards_dealt() {
+        let mut report = SequencingReport::default();
+        for card in &Deck::of_type(&DeckType::Spanish).cards {
+            report.record_card(card);
+        }
+        // Four aces in 48 cards
+        assert!((report.chance_rate(1) - 1.0 / 12.0).abs() < 1e-9);
+        assert!((report.chance_rate(2) - (1.0 - (11.0_f64 / 12.0).powi(2))).abs() < 1e-9);
+    }
+}
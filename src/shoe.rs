@@ -0,0 +1,419 @@
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{DeckType, GameConfig, ShoeRules},
+    error::Error,
+    shuffle::ShuffleRecord,
+    sidebets::{CountTags, SideCount},
+    types::{Card, Deck},
+};
+
+/// A source of cards for the game
+///
+/// The game only ever talks to the shoe through this trait, so new ways of dealing cards
+/// can be added without touching the game logic.
+#[typetag::serde(tag = "shoe")]
+pub trait Shoe {
+    /// Draw the next card to be dealt
+    fn draw(&mut self) -> Card;
+    /// Number of cards left to be dealt before the shoe runs out, `None` for a shoe that
+    /// never runs out
+    fn cards_remaining(&self) -> Option<usize>;
+    /// Cards left to be dealt grouped by value
+    fn composition(&self) -> Composition;
+    /// True once the shoe has to be shuffled before the next round is dealt
+    fn needs_reshuffle(&self) -> bool;
+    /// Shuffle the shoe back together
+    fn reshuffle(&mut self);
+    /// Hi-Lo running count of the cards dealt since the last shuffle
+    fn running_count(&self) -> isize;
+    /// Keep a side count with the given tags alongside the Hi-Lo count
+    fn keep_side_count(&mut self, _tags: CountTags) {}
+    /// Running count of the side count since the last shuffle, 0 when the shoe keeps none
+    fn side_running_count(&self) -> isize {
+        0
+    }
+    /// Hand the cards from the last round back to the shoe
+    fn discard(&mut self, _cards: Vec<Card>) {}
+    /// The pile the current shoe was shuffled from, for shoes rebuilt from their discards
+    fn last_shuffle(&self) -> Option<&ShuffleRecord> {
+        None
+    }
+    /// True when the shoe was just shuffled and is waiting for a player to cut it
+    fn awaiting_cut(&self) -> bool {
+        false
+    }
+    /// Cut the shoe `position` cards from the top, or at a random spot for `None`
+    fn cut(&mut self, _position: Option<usize>) {}
+}
+
+/// Creates the shoe described by the table rules
+pub fn create_shoe(config: &GameConfig) -> Box<dyn Shoe> {
+    match config.shoe_rules {
+        ShoeRules::HandShuffled => {
+            let mut deck = Deck::create_shoe(config.reserve_decks, &config.deck_type);
+            deck.shuffle_routine = config.shuffle_routine.clone();
+            Box::new(deck)
+        }
+        ShoeRules::ContinuousShuffle { reserve_cards } => {
+            Box::new(ShufflingMachine::new(config.reserve_decks, &config.deck_type, reserve_cards))
+        }
+        ShoeRules::InfiniteDeck => Box::new(InfiniteShoe::new(&config.deck_type)),
+    }
+}
+
+/// Number of cards of each value, ten valued cards are counted together
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Composition {
+    /// Aces first, then twos through nines, then ten valued cards
+    counts: [usize; 10],
+}
+
+impl Composition {
+    pub fn from_cards<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Self {
+        let mut this = Self::default();
+        for card in cards {
+            this.add(card);
+        }
+        this
+    }
+
+    pub fn add(&mut self, card: &Card) {
+        if !card.cut_card {
+            self.counts[Self::index(card.face_value())] += 1;
+        }
+    }
+
+    pub fn remove(&mut self, card: &Card) {
+        if !card.cut_card {
+            let count = &mut self.counts[Self::index(card.face_value())];
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Number of cards with the given value, aces can be asked for as 1 or 11
+    pub fn count(&self, value: u8) -> usize {
+        self.counts[Self::index(value)]
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Chance the next card has the given value
+    pub fn probability(&self, value: u8) -> f64 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        self.count(value) as f64 / total as f64
+    }
+
+    fn index(value: u8) -> usize {
+        match value {
+            1 | 11 => 0,
+            2..=10 => value as usize - 1,
+            _ => panic!("Unexpected card value: {}", value),
+        }
+    }
+}
+
+#[typetag::serde]
+impl Shoe for Deck {
+    fn draw(&mut self) -> Card {
+        if self.awaiting_cut {
+            // Nobody cut the shoe, so the dealer picks a spot
+            Deck::cut(self, None);
+        }
+        let card = self.cards.pop().unwrap();
+        let card_count = card.count();
+        self.count += card_count; // Update the count based on the card drawn
+
+        let card = if card.cut_card {
+            // Take the next card after the cut card
+            self.cut_card_drawn = true;
+            self.cards.pop().unwrap()
+        } else {
+            card
+        };
+        if let Some(side_count) = self.side_count.as_mut() {
+            side_count.deal(&card);
+        }
+        card
+    }
+    fn cards_remaining(&self) -> Option<usize> {
+        Some(self.cards.iter().filter(|card| !card.cut_card).count())
+    }
+    fn composition(&self) -> Composition {
+        Composition::from_cards(&self.cards)
+    }
+    fn needs_reshuffle(&self) -> bool {
+        self.cut_card_drawn
+    }
+    fn reshuffle(&mut self) {
+        self.shuffle_discards();
+    }
+    fn running_count(&self) -> isize {
+        self.count
+    }
+    fn keep_side_count(&mut self, tags: CountTags) {
+        self.side_count = Some(SideCount::new(tags));
+    }
+    fn side_running_count(&self) -> isize {
+        self.side_count.as_ref().map_or(0, |side_count| side_count.running_count)
+    }
+    /// Discards stay in the tray until the next shuffle
+    fn discard(&mut self, cards: Vec<Card>) {
+        self.discards.extend(cards);
+    }
+    fn last_shuffle(&self) -> Option<&ShuffleRecord> {
+        self.last_shuffle.as_ref()
+    }
+    fn awaiting_cut(&self) -> bool {
+        self.awaiting_cut
+    }
+    fn cut(&mut self, position: Option<usize>) {
+        Deck::cut(self, position);
+    }
+}
+
+/// A continuous shuffling machine
+///
+/// Cards are dealt from a small reserve of already shuffled cards. Discards are fed back
+/// into the machine after every round, so the shoe never runs out and never needs shuffling.
+#[derive(Serialize, Deserialize)]
+pub struct ShufflingMachine {
+    /// Cards loaded in the machine that have not been shuffled into the reserve yet
+    machine: Vec<Card>,
+    /// Shuffled cards ready to be dealt, the next card is at the end
+    reserve: Vec<Card>,
+    /// Number of cards the machine keeps ready in the reserve
+    reserve_cards: usize,
+    count: isize,
+    side_count: Option<SideCount>,
+}
+
+impl ShufflingMachine {
+    /// Loads the specified number of decks into a new machine.
+    pub fn new(decks: usize, deck_type: &DeckType, reserve_cards: usize) -> Self {
+        let mut machine = Vec::new();
+        for _ in 0..decks {
+            machine.extend(Deck::of_type(deck_type).cards);
+        }
+        let mut this = Self { machine, reserve: Vec::new(), reserve_cards, count: 0, side_count: None };
+        this.fill_reserve();
+        this
+    }
+
+    fn fill_reserve(&mut self) {
+        while self.reserve.len() < self.reserve_cards && !self.machine.is_empty() {
+            let card = self.take_random_card();
+            // New cards drop in at the bottom of the reserve
+            self.reserve.insert(0, card);
+        }
+    }
+
+    fn take_random_card(&mut self) -> Card {
+        let index = thread_rng().gen_range(0..self.machine.len());
+        self.machine.swap_remove(index)
+    }
+}
+
+#[typetag::serde]
+impl Shoe for ShufflingMachine {
+    fn draw(&mut self) -> Card {
+        if self.reserve.is_empty() {
+            // No reserve, deal straight from the machine
+            let card = self.take_random_card();
+            self.reserve.push(card);
+        }
+        let card = self.reserve.pop().unwrap();
+        self.count += card.count();
+        if let Some(side_count) = self.side_count.as_mut() {
+            side_count.deal(&card);
+        }
+        self.fill_reserve();
+
+        card
+    }
+    fn cards_remaining(&self) -> Option<usize> {
+        Some(self.machine.len() + self.reserve.len())
+    }
+    fn composition(&self) -> Composition {
+        Composition::from_cards(self.machine.iter().chain(self.reserve.iter()))
+    }
+    fn needs_reshuffle(&self) -> bool {
+        false // The machine shuffles as it goes
+    }
+    fn reshuffle(&mut self) {}
+    fn running_count(&self) -> isize {
+        self.count
+    }
+    fn keep_side_count(&mut self, tags: CountTags) {
+        self.side_count = Some(SideCount::new(tags));
+    }
+    fn side_running_count(&self) -> isize {
+        self.side_count.as_ref().map_or(0, |side_count| side_count.running_count)
+    }
+    /// Feed the cards from the last round back into the machine
+    fn discard(&mut self, cards: Vec<Card>) {
+        for card in cards {
+            self.count -= card.count();
+            if let Some(side_count) = self.side_count.as_mut() {
+                side_count.return_card(&card);
+            }
+            self.machine.push(card);
+        }
+    }
+}
+
+/// A shoe with an infinite number of decks
+///
+/// Every card is drawn with the same probability as from a fresh deck, so removing cards
+/// never changes the odds. Useful for comparing against published infinite deck numbers.
+#[derive(Default, Serialize, Deserialize)]
+pub struct InfiniteShoe {
+    /// One deck giving the relative frequency of each card
+    deck: Deck,
+}
+
+impl InfiniteShoe {
+    pub fn new(deck_type: &DeckType) -> Self {
+        Self { deck: Deck::of_type(deck_type) }
+    }
+}
+
+#[typetag::serde]
+impl Shoe for InfiniteShoe {
+    fn draw(&mut self) -> Card {
+        let index = thread_rng().gen_range(0..self.deck.cards.len());
+        self.deck.cards[index].clone()
+    }
+    fn cards_remaining(&self) -> Option<usize> {
+        None
+    }
+    fn composition(&self) -> Composition {
+        Composition::from_cards(&self.deck.cards)
+    }
+    fn needs_reshuffle(&self) -> bool {
+        false
+    }
+    fn reshuffle(&mut self) {}
+    fn running_count(&self) -> isize {
+        0 // Drawn cards are never removed, so the count can't move
+    }
+}
+
+/// A shoe that deals a fixed list of cards in order
+///
+/// Used to set up specific situations and reproduce reported hands. The game deals the
+/// dealer's up card, the dealer's hole card and then the player's two cards, after which
+/// every hit, double and split card is drawn in turn. Without a hole card the dealer's
+/// second card is drawn after the player's last one.
+///
+/// The stack has to hold every card the round draws, dealing past the last card panics
+/// with "Stacked shoe ran out of cards" so a short stack fails the test that set it up.
+#[derive(Serialize, Deserialize)]
+pub struct StackedShoe {
+    /// Cards left to deal, the next card is at the end
+    cards: Vec<Card>,
+    count: isize,
+    side_count: Option<SideCount>,
+}
+
+impl StackedShoe {
+    /// Cards are given in the order they are dealt
+    pub fn new(mut cards: Vec<Card>) -> Self {
+        cards.reverse();
+        Self { cards, count: 0, side_count: None }
+    }
+
+    /// Parses cards separated by whitespace in the order they are dealt
+    ///
+    /// `|` can be used to group the cards by hand and is otherwise ignored, so
+    /// `"T♠ 6♥ | 8♦ 8♣ | 3♠ K♥"` deals the dealer T♠ up and 6♥ in the hole, gives the
+    /// player a pair of eights and then draws 3♠ and K♥.
+    pub fn parse(notation: &str) -> Result<Self, Error> {
+        let cards = notation
+            .split(|c: char| c.is_whitespace() || c == '|')
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse())
+            .collect::<Result<Vec<Card>, Error>>()?;
+        Ok(Self::new(cards))
+    }
+}
+
+#[typetag::serde]
+impl Shoe for StackedShoe {
+    fn draw(&mut self) -> Card {
+        let card = self.cards.pop().expect("Stacked shoe ran out of cards");
+        self.count += card.count();
+        if let Some(side_count) = self.side_count.as_mut() {
+            side_count.deal(&card);
+        }
+        card
+    }
+    fn cards_remaining(&self) -> Option<usize> {
+        Some(self.cards.len())
+    }
+    fn composition(&self) -> Composition {
+        Composition::from_cards(&self.cards)
+    }
+    fn needs_reshuffle(&self) -> bool {
+        false // Keeps dealing the stack in order
+    }
+    fn reshuffle(&mut self) {}
+    fn running_count(&self) -> isize {
+        self.count
+    }
+    fn keep_side_count(&mut self, tags: CountTags) {
+        self.side_count = Some(SideCount::new(tags));
+    }
+    fn side_running_count(&self) -> isize {
+        self.side_count.as_ref().map_or(0, |side_count| side_count.running_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_card_counts_one() -> CountTags {
+        CountTags { name: "Cards".to_string(), tags: [1; 10] }
+    }
+
+    #[test]
+    fn side_count_takes_back_cards_fed_into_the_machine() {
+        let mut machine = ShufflingMachine::new(1, &DeckType::Standard, 20);
+        machine.keep_side_count(every_card_counts_one());
+        let cards: Vec<Card> = (0..5).map(|_| machine.draw()).collect();
+        assert_eq!(machine.side_running_count(), 5);
+        machine.discard(cards);
+        assert_eq!(machine.side_running_count(), 0);
+    }
+
+    #[test]
+    fn infinite_deck_keeps_no_side_count() {
+        let mut shoe = InfiniteShoe::new(&DeckType::Standard);
+        shoe.keep_side_count(every_card_counts_one());
+        shoe.draw();
+        assert_eq!(shoe.side_running_count(), 0);
+    }
+
+    /// Cards left behind the cut card after cutting a fresh shoe of `decks` Spanish decks
+    fn behind_cut_card(decks: usize) -> usize {
+        let mut deck = Deck::create_shoe(decks, &DeckType::Spanish);
+        deck.cut(None);
+        deck.cards.iter().position(|card| card.cut_card).unwrap()
+    }
+
+    #[test]
+    fn places_the_cut_card_by_the_size_of_a_spanish_deck() {
+        for _ in 0..100 {
+            // Three quarters of a single deck and a deck and a half of six
+            assert!((31..=41).contains(&behind_cut_card(1)));
+            assert!((67..=77).contains(&behind_cut_card(6)));
+        }
+    }
+}
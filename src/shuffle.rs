@@ -0,0 +1,260 @@
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::types::Card;
+
+/// How the dealer shuffles the discards back into a new shoe
+///
+/// Piles are ordered bottom to top, so the last card of a pile is the top card.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ShuffleRoutine {
+    /// A perfectly random shuffle
+    Uniform,
+    /// The dealer works through the steps in order
+    Steps(Vec<ShuffleStep>),
+}
+
+impl ShuffleRoutine {
+    /// Riffle, strip, riffle on every pick up followed by a cut, a common casino routine
+    pub fn riffle_strip_riffle() -> Self {
+        ShuffleRoutine::Steps(vec![
+            ShuffleStep::PickUp {
+                grab: 26,
+                steps: vec![ShuffleStep::Riffle, ShuffleStep::Strip { packets: 4 }, ShuffleStep::Riffle],
+            },
+            ShuffleStep::Cut,
+        ])
+    }
+
+    /// A single riffle on every pick up, about the least a casino gets away with
+    pub fn one_pass() -> Self {
+        ShuffleRoutine::Steps(vec![
+            ShuffleStep::PickUp { grab: 26, steps: vec![ShuffleStep::Riffle] },
+            ShuffleStep::Cut,
+        ])
+    }
+
+    /// Box the shoe, then two full pick up passes
+    pub fn box_and_riffle() -> Self {
+        ShuffleRoutine::Steps(vec![
+            ShuffleStep::Box { piles: 4 },
+            ShuffleStep::PickUp { grab: 26, steps: vec![ShuffleStep::Riffle, ShuffleStep::Riffle] },
+            ShuffleStep::PickUp { grab: 26, steps: vec![ShuffleStep::Riffle] },
+            ShuffleStep::Cut,
+        ])
+    }
+
+    /// Shuffle a pile in place
+    pub fn apply<T>(&self, pile: &mut Vec<T>) {
+        let mut rng = thread_rng();
+        match self {
+            ShuffleRoutine::Uniform => pile.shuffle(&mut rng),
+            ShuffleRoutine::Steps(steps) => {
+                for step in steps {
+                    step.apply(pile, &mut rng);
+                }
+            }
+        }
+    }
+}
+
+/// What went into the last shuffle, as seen by a player at the table
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ShuffleRecord {
+    /// Number of shoes shuffled so far, starting at 1 for the first reshuffle
+    pub shoe: usize,
+    /// Discards in the order they were picked up, bottom of the pile first
+    pub discards: Vec<Card>,
+    /// Cards left behind the cut card, placed on top of the discards without being seen
+    pub unseen: usize,
+    /// Number of cards from the top the shuffled pile was cut at
+    pub cut: usize,
+}
+
+impl ShuffleRecord {
+    /// Number of cards in the shuffled pile
+    pub fn len(&self) -> usize {
+        self.discards.len() + self.unseen
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A single move in a dealer's shuffle
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ShuffleStep {
+    /// Gilbert–Shannon–Reeds riffle of the whole pile
+    ///
+    /// The pile is cut binomially and cards drop from each half with probability
+    /// proportional to the number of cards left in that half.
+    Riffle,
+    /// Strip the pile into roughly `packets` packets of uneven size, reversing their order
+    Strip { packets: usize },
+    /// Split the pile into `piles` even piles and stack them back in reverse order
+    Box { piles: usize },
+    /// Cut the pile somewhere near the middle and swap the halves
+    Cut,
+    /// Split the pile into two halves, then repeatedly take `grab` cards from the top of
+    /// each half, run the steps on them and stack the result on a new pile
+    PickUp { grab: usize, steps: Vec<ShuffleStep> },
+}
+
+impl ShuffleStep {
+    pub fn apply<T>(&self, pile: &mut Vec<T>, rng: &mut impl Rng) {
+        match self {
+            ShuffleStep::Riffle => riffle(pile, rng),
+            ShuffleStep::Strip { packets } => strip(pile, *packets, rng),
+            ShuffleStep::Box { piles } => box_shuffle(pile, *piles),
+            ShuffleStep::Cut => cut(pile, rng),
+            ShuffleStep::PickUp { grab, steps } => pick_up(pile, *grab, steps, rng),
+        }
+    }
+}
+
+fn riffle<T>(pile: &mut Vec<T>, rng: &mut impl Rng) {
+    let cut_at = (0..pile.len()).filter(|_| rng.gen_bool(0.5)).count();
+    let mut right = pile.split_off(cut_at).into_iter();
+    let mut left = std::mem::take(pile).into_iter();
+
+    // Cards fall from the bottom of each half
+    while left.len() + right.len() > 0 {
+        let from_left = rng.gen_range(0..left.len() + right.len()) < left.len();
+        let card = if from_left { left.next() } else { right.next() };
+        pile.push(card.unwrap());
+    }
+}
+
+fn strip<T>(pile: &mut Vec<T>, packets: usize, rng: &mut impl Rng) {
+    let average = (pile.len() / packets.max(1)).max(1);
+    let mut stripped = Vec::with_capacity(pile.len());
+    while !pile.is_empty() {
+        // Packets vary by about a third either way
+        let spread = average / 3;
+        let size = rng.gen_range(average - spread..=average + spread).min(pile.len());
+        let packet = pile.split_off(pile.len() - size);
+        stripped.extend(packet);
+    }
+    *pile = stripped;
+}
+
+fn box_shuffle<T>(pile: &mut Vec<T>, piles: usize) {
+    let size = pile.len().div_ceil(piles.max(1)).max(1);
+    let mut boxed = Vec::with_capacity(pile.len());
+    while !pile.is_empty() {
+        let packet = pile.split_off(pile.len().saturating_sub(size));
+        boxed.extend(packet);
+    }
+    *pile = boxed;
+}
+
+fn cut<T>(pile: &mut [T], rng: &mut impl Rng) {
+    if pile.is_empty() {
+        return;
+    }
+    let spread = pile.len() / 10;
+    let cut_at = rng.gen_range(pile.len() / 2 - spread..=pile.len() / 2 + spread);
+    pile.rotate_left(cut_at);
+}
+
+fn pick_up<T>(pile: &mut Vec<T>, grab: usize, steps: &[ShuffleStep], rng: &mut impl Rng) {
+    let mut right = pile.split_off(pile.len() / 2);
+    let mut left = std::mem::take(pile);
+    while !left.is_empty() || !right.is_empty() {
+        let mut packet = right.split_off(right.len().saturating_sub(grab));
+        let left_grab = left.split_off(left.len().saturating_sub(grab));
+        packet.extend(left_grab);
+        for step in steps {
+            step.apply(&mut packet, rng);
+        }
+        pile.extend(packet);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shoe(cards: usize) -> Vec<usize> {
+        (0..cards).collect()
+    }
+
+    fn is_permutation(pile: &[usize], cards: usize) -> bool {
+        let mut sorted = pile.to_vec();
+        sorted.sort();
+        sorted == shoe(cards)
+    }
+
+    /// Splits the pile where it stops counting up, each run of cards kept together
+    fn runs(pile: &[usize]) -> Vec<&[usize]> {
+        pile.chunk_by(|a, b| *b == a + 1).collect()
+    }
+
+    /// Number of rising sequences, a riffle of a sorted pile leaves at most two
+    fn rising_sequences(pile: &[usize]) -> usize {
+        let mut position = vec![0; pile.len()];
+        for (i, &card) in pile.iter().enumerate() {
+            position[card] = i;
+        }
+        1 + position.windows(2).filter(|pair| pair[1] < pair[0]).count()
+    }
+
+    #[test]
+    fn riffle_interleaves_the_two_halves() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let mut pile = shoe(52);
+            riffle(&mut pile, &mut rng);
+            assert!(is_permutation(&pile, 52));
+            assert!(rising_sequences(&pile) <= 2);
+        }
+    }
+
+    #[test]
+    fn strip_reverses_the_order_of_the_packets() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let mut pile = shoe(52);
+            strip(&mut pile, 4, &mut rng);
+            assert!(is_permutation(&pile, 52));
+            // Putting the packets back in reverse order gives the pile as it was
+            let unstripped: Vec<usize> = runs(&pile).into_iter().rev().flatten().copied().collect();
+            assert_eq!(unstripped, shoe(52));
+        }
+    }
+
+    #[test]
+    fn box_stacks_even_piles_in_reverse_order() {
+        let mut pile = shoe(52);
+        box_shuffle(&mut pile, 4);
+        let expected: Vec<usize> = (39..52).chain(26..39).chain(13..26).chain(0..13).collect();
+        assert_eq!(pile, expected);
+    }
+
+    #[test]
+    fn cut_swaps_the_halves_near_the_middle() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let mut pile = shoe(52);
+            cut(&mut pile, &mut rng);
+            assert!(is_permutation(&pile, 52));
+            // What was the top of the pile is now the bottom
+            assert!((21..=31).contains(&pile[0]));
+            assert_eq!(runs(&pile).len(), 2);
+        }
+    }
+
+    #[test]
+    fn pick_up_and_routines_keep_every_card() {
+        let mut rng = thread_rng();
+        let mut pile = shoe(312);
+        pick_up(&mut pile, 26, &[ShuffleStep::Riffle, ShuffleStep::Strip { packets: 4 }], &mut rng);
+        assert!(is_permutation(&pile, 312));
+        for routine in [ShuffleRoutine::riffle_strip_riffle(), ShuffleRoutine::one_pass(), ShuffleRoutine::box_and_riffle()] {
+            let mut pile = shoe(312);
+            routine.apply(&mut pile);
+            assert!(is_permutation(&pile, 312));
+        }
+    }
+}
@@ -0,0 +1,521 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{money::Money, types::{Card, CardFace, Face, Suit}};
+
+/// What a winning side bet pays, `win` for every `per` staked
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Odds {
+    pub win: u32,
+    pub per: u32,
+}
+
+impl Odds {
+    pub const fn to_one(win: u32) -> Self {
+        Self { win, per: 1 }
+    }
+
+    /// Winnings on `bet`, rounded down to the cent
+    pub fn winning_amount(&self, bet: Money) -> Money {
+        bet.times(self.win, self.per)
+    }
+}
+
+impl std::fmt::Display for Odds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.win, self.per)
+    }
+}
+
+/// A bet on the first cards of the round, made alongside the main bet and settled with it
+///
+/// Side bets are settled on the player's first two cards and the dealer's cards as they
+/// were dealt, the up card first and then the hole card if the dealer took one. Bets that
+/// need both dealer cards wait for the second one at a table without a hole card.
+#[typetag::serde(tag = "bet")]
+pub trait SideBet: SideBetClone {
+    fn name(&self) -> &'static str;
+    /// What the bet pays on these cards, `None` if it loses
+    fn odds(&self, player: &[Card], dealer: &[Card]) -> Option<Odds>;
+    /// True for bets settled on the dealer's finished hand instead of the first cards, the
+    /// dealer then plays out the hand even when no player hand is left standing
+    fn waits_for_dealer(&self) -> bool {
+        false
+    }
+    /// True for bets that pay on the dealer's first two cards, the dealer's second card is
+    /// then dealt for them even when no player hand is left standing
+    fn needs_both_dealer_cards(&self) -> bool {
+        false
+    }
+}
+
+/// Lets the side bets on a [`crate::config::GameConfig`] be cloned with it
+pub trait SideBetClone {
+    fn clone_box(&self) -> Box<dyn SideBet>;
+}
+
+impl<T: SideBet + Clone + 'static> SideBetClone for T {
+    fn clone_box(&self) -> Box<dyn SideBet> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn SideBet> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// How a side bet placed this round was settled
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SideBetResult {
+    pub name: String,
+    pub wager: Money,
+    /// Set once the bet has been settled, bets waiting on the dealer are settled last
+    pub settled: bool,
+    /// What the bet paid, `None` if it lost
+    pub odds: Option<Odds>,
+    /// True count when the bet was placed, before the round was dealt, by the side count
+    /// if the table has one
+    pub true_count: isize,
+}
+
+impl SideBetResult {
+    /// Amount handed back to the player, the wager included
+    pub fn returned(&self) -> Money {
+        self.odds.map_or(Money::ZERO, |odds| self.wager + odds.winning_amount(self.wager))
+    }
+}
+
+impl std::fmt::Display for SideBetResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.odds {
+            _ if !self.settled => write!(f, "{} {} waits for the dealer", self.name, self.wager),
+            Some(odds) => write!(f, "{} {} pays {}", self.name, self.wager, odds),
+            None => write!(f, "{} {} loses", self.name, self.wager),
+        }
+    }
+}
+
+/// Card counting tags for timing a side bet, kept alongside the Hi-Lo count
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CountTags {
+    pub name: String,
+    /// Tags for 2 through 9, ten-valued cards and aces in that order
+    pub tags: [isize; 10],
+}
+
+impl CountTags {
+    pub fn tag(&self, card: &Card) -> isize {
+        self.tags[card.face_value() as usize - 2]
+    }
+}
+
+/// Running count of a side count, kept by the shoe the same way as its Hi-Lo count
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SideCount {
+    pub tags: CountTags,
+    pub running_count: isize,
+}
+
+impl SideCount {
+    pub fn new(tags: CountTags) -> Self {
+        Self { tags, running_count: 0 }
+    }
+
+    /// Counts a card dealt from the shoe
+    pub fn deal(&mut self, card: &Card) {
+        if !card.cut_card {
+            self.running_count += self.tags.tag(card);
+        }
+    }
+
+    /// Takes a card fed back into the shoe out of the count
+    pub fn return_card(&mut self, card: &Card) {
+        if !card.cut_card {
+            self.running_count -= self.tags.tag(card);
+        }
+    }
+}
+
+/// Poker hand made from the player's two cards and the dealer's up card
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TwentyOnePlusThree {
+    pub suited_trips: Odds,
+    pub straight_flush: Odds,
+    pub three_of_a_kind: Odds,
+    pub straight: Odds,
+    pub flush: Odds,
+}
+
+impl Default for TwentyOnePlusThree {
+    fn default() -> Self {
+        Self {
+            suited_trips: Odds::to_one(100),
+            straight_flush: Odds::to_one(40),
+            three_of_a_kind: Odds::to_one(30),
+            straight: Odds::to_one(10),
+            flush: Odds::to_one(5),
+        }
+    }
+}
+
+#[typetag::serde]
+impl SideBet for TwentyOnePlusThree {
+    fn name(&self) -> &'static str {
+        "21+3"
+    }
+
+    fn odds(&self, player: &[Card], dealer: &[Card]) -> Option<Odds> {
+        let cards = [player.first()?, player.get(1)?, dealer.first()?];
+        let flush = cards.iter().all(|card| card.suit == cards[0].suit);
+        let trips = cards.iter().all(|card| rank(card) == rank(cards[0]));
+        let mut ranks = cards.map(rank);
+        ranks.sort();
+        // Aces play high or low, but a straight can't go round the corner
+        let straight = ranks[1] == ranks[0] + 1 && ranks[2] == ranks[1] + 1 || ranks == [1, 12, 13];
+        match (trips, straight, flush) {
+            (true, _, true) => Some(self.suited_trips),
+            (_, true, true) => Some(self.straight_flush),
+            (true, _, _) => Some(self.three_of_a_kind),
+            (_, true, _) => Some(self.straight),
+            (_, _, true) => Some(self.flush),
+            _ => None,
+        }
+    }
+}
+
+/// The player's first two cards make a pair
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PerfectPairs {
+    /// Same rank and suit
+    pub perfect: Odds,
+    /// Same rank and colour
+    pub coloured: Odds,
+    /// Same rank, one red and one black
+    pub mixed: Odds,
+}
+
+impl Default for PerfectPairs {
+    fn default() -> Self {
+        Self {
+            perfect: Odds::to_one(25),
+            coloured: Odds::to_one(12),
+            mixed: Odds::to_one(6),
+        }
+    }
+}
+
+#[typetag::serde]
+impl SideBet for PerfectPairs {
+    fn name(&self) -> &'static str {
+        "Perfect Pairs"
+    }
+
+    fn odds(&self, player: &[Card], _dealer: &[Card]) -> Option<Odds> {
+        let [first, second, ..] = player else {
+            return None;
+        };
+        if rank(first) != rank(second) {
+            return None;
+        }
+        Some(if first.suit == second.suit {
+            self.perfect
+        } else if is_red(first) == is_red(second) {
+            self.coloured
+        } else {
+            self.mixed
+        })
+    }
+}
+
+/// The player's first two cards total 20, paying most for the queens of hearts
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LuckyLadies {
+    /// A pair of queens of hearts against a dealer blackjack
+    pub queens_of_hearts_with_dealer_blackjack: Odds,
+    pub queens_of_hearts: Odds,
+    /// Two cards of the same rank and suit
+    pub matched_20: Odds,
+    pub suited_20: Odds,
+    pub any_20: Odds,
+}
+
+impl Default for LuckyLadies {
+    fn default() -> Self {
+        Self {
+            queens_of_hearts_with_dealer_blackjack: Odds::to_one(1000),
+            queens_of_hearts: Odds::to_one(125),
+            matched_20: Odds::to_one(19),
+            suited_20: Odds::to_one(9),
+            any_20: Odds::to_one(4),
+        }
+    }
+}
+
+#[typetag::serde]
+impl SideBet for LuckyLadies {
+    fn name(&self) -> &'static str {
+        "Lucky Ladies"
+    }
+
+    fn odds(&self, player: &[Card], dealer: &[Card]) -> Option<Odds> {
+        let [first, second, ..] = player else {
+            return None;
+        };
+        if first.face_value() + second.face_value() != 20 {
+            return None;
+        }
+        let queen_of_hearts = |card: &Card| card.face == CardFace::Face(Face::Queen) && card.suit == Suit::Hearts;
+        let dealer_blackjack = match dealer {
+            [up, hole, ..] => up.face_value() + hole.face_value() == 21,
+            _ => false,
+        };
+        let matched = rank(first) == rank(second) && first.suit == second.suit;
+        Some(if queen_of_hearts(first) && queen_of_hearts(second) {
+            if dealer_blackjack {
+                self.queens_of_hearts_with_dealer_blackjack
+            } else {
+                self.queens_of_hearts
+            }
+        } else if matched {
+            self.matched_20
+        } else if first.suit == second.suit {
+            self.suited_20
+        } else {
+            self.any_20
+        })
+    }
+
+    fn needs_both_dealer_cards(&self) -> bool {
+        true
+    }
+}
+
+/// The player's first two cards are suited, paying most for a king and queen
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoyalMatch {
+    pub royal_match: Odds,
+    pub suited: Odds,
+}
+
+impl Default for RoyalMatch {
+    fn default() -> Self {
+        Self {
+            royal_match: Odds::to_one(25),
+            suited: Odds { win: 5, per: 2 },
+        }
+    }
+}
+
+#[typetag::serde]
+impl SideBet for RoyalMatch {
+    fn name(&self) -> &'static str {
+        "Royal Match"
+    }
+
+    fn odds(&self, player: &[Card], _dealer: &[Card]) -> Option<Odds> {
+        let [first, second, ..] = player else {
+            return None;
+        };
+        if first.suit != second.suit {
+            return None;
+        }
+        let mut ranks = [rank(first), rank(second)];
+        ranks.sort();
+        Some(if ranks == [12, 13] { self.royal_match } else { self.suited })
+    }
+}
+
+/// The player's first two cards total over or under 13 with aces as one, 13 loses either way
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OverUnder13 {
+    /// Betting the total is over 13 rather than under it
+    pub over: bool,
+    pub odds: Odds,
+}
+
+impl OverUnder13 {
+    pub fn over() -> Self {
+        Self { over: true, odds: Odds::to_one(1) }
+    }
+
+    pub fn under() -> Self {
+        Self { over: false, odds: Odds::to_one(1) }
+    }
+}
+
+#[typetag::serde]
+impl SideBet for OverUnder13 {
+    fn name(&self) -> &'static str {
+        if self.over { "Over 13" } else { "Under 13" }
+    }
+
+    fn odds(&self, player: &[Card], _dealer: &[Card]) -> Option<Odds> {
+        let [first, second, ..] = player else {
+            return None;
+        };
+        let total = blackjack_value(first) + blackjack_value(second);
+        let wins = if self.over { total > 13 } else { total < 13 };
+        wins.then_some(self.odds)
+    }
+}
+
+/// The dealer busts, paying more the more cards the bust took
+///
+/// Pays 1:1 for three cards up to 250:1 for eight or more by default, the paytable most
+/// Buster Blackjack tables deal.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Buster {
+    pub three_cards: Odds,
+    pub four_cards: Odds,
+    pub five_cards: Odds,
+    pub six_cards: Odds,
+    pub seven_cards: Odds,
+    pub eight_or_more_cards: Odds,
+}
+
+impl Default for Buster {
+    fn default() -> Self {
+        Self {
+            three_cards: Odds::to_one(1),
+            four_cards: Odds::to_one(2),
+            five_cards: Odds::to_one(4),
+            six_cards: Odds::to_one(18),
+            seven_cards: Odds::to_one(50),
+            eight_or_more_cards: Odds::to_one(250),
+        }
+    }
+}
+
+#[typetag::serde]
+impl SideBet for Buster {
+    fn name(&self) -> &'static str {
+        "Buster"
+    }
+
+    fn odds(&self, _player: &[Card], dealer: &[Card]) -> Option<Odds> {
+        let total: u8 = dealer.iter()
+            .map(|card| if card.face == CardFace::Ace { 1 } else { card.face_value() })
+            .sum();
+        if total <= 21 {
+            return None;
+        }
+        match dealer.len() {
+            0..=2 => None,
+            3 => Some(self.three_cards),
+            4 => Some(self.four_cards),
+            5 => Some(self.five_cards),
+            6 => Some(self.six_cards),
+            7 => Some(self.seven_cards),
+            _ => Some(self.eight_or_more_cards),
+        }
+    }
+
+    fn waits_for_dealer(&self) -> bool {
+        true
+    }
+}
+
+/// Poker rank, aces low
+fn rank(card: &Card) -> u8 {
+    match &card.face {
+        CardFace::Ace => 1,
+        CardFace::Number(n) => *n,
+        CardFace::Face(Face::Jack) => 11,
+        CardFace::Face(Face::Queen) => 12,
+        CardFace::Face(Face::King) => 13,
+    }
+}
+
+/// Blackjack value with aces counted as one
+fn blackjack_value(card: &Card) -> u8 {
+    match card.face {
+        CardFace::Ace => 1,
+        _ => card.face_value(),
+    }
+}
+
+fn is_red(card: &Card) -> bool {
+    matches!(card.suit, Suit::Hearts | Suit::Diamonds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(notation: &str) -> Vec<Card> {
+        notation.split_whitespace().map(|card| card.parse().unwrap()).collect()
+    }
+
+    /// What `bet` pays on the player's two cards against the dealer's cards
+    fn pays(bet: &dyn SideBet, player: &str, dealer: &str) -> Option<Odds> {
+        bet.odds(&cards(player), &cards(dealer))
+    }
+
+    #[test]
+    fn twenty_one_plus_three_pays_each_poker_hand() {
+        let bet = TwentyOnePlusThree::default();
+        assert_eq!(pays(&bet, "7♥ 7♥", "7♥ 2♣"), Some(Odds::to_one(100)));
+        assert_eq!(pays(&bet, "8♠ 9♠", "T♠ 2♣"), Some(Odds::to_one(40)));
+        assert_eq!(pays(&bet, "7♥ 7♠", "7♦ 2♣"), Some(Odds::to_one(30)));
+        assert_eq!(pays(&bet, "Q♥ K♠", "A♦ 2♣"), Some(Odds::to_one(10)));
+        assert_eq!(pays(&bet, "A♥ 2♠", "3♦ 2♣"), Some(Odds::to_one(10)));
+        assert_eq!(pays(&bet, "2♣ 9♣", "K♣ 2♥"), Some(Odds::to_one(5)));
+        // Only the up card plays, and a straight can't go round the corner
+        assert_eq!(pays(&bet, "K♥ A♠", "2♦ Q♣"), None);
+    }
+
+    #[test]
+    fn perfect_pairs_pays_by_how_well_the_pair_matches() {
+        let bet = PerfectPairs::default();
+        assert_eq!(pays(&bet, "8♠ 8♠", "2♣"), Some(Odds::to_one(25)));
+        assert_eq!(pays(&bet, "8♥ 8♦", "2♣"), Some(Odds::to_one(12)));
+        assert_eq!(pays(&bet, "8♥ 8♠", "2♣"), Some(Odds::to_one(6)));
+        // Ten valued cards of different ranks are no pair
+        assert_eq!(pays(&bet, "K♥ Q♥", "2♣"), None);
+    }
+
+    #[test]
+    fn lucky_ladies_pays_each_kind_of_20() {
+        let bet = LuckyLadies::default();
+        assert_eq!(pays(&bet, "Q♥ Q♥", "A♠ K♣"), Some(Odds::to_one(1000)));
+        assert_eq!(pays(&bet, "Q♥ Q♥", "9♠ 7♣"), Some(Odds::to_one(125)));
+        assert_eq!(pays(&bet, "K♠ K♠", "9♠ 7♣"), Some(Odds::to_one(19)));
+        assert_eq!(pays(&bet, "K♠ Q♠", "9♠ 7♣"), Some(Odds::to_one(9)));
+        assert_eq!(pays(&bet, "A♦ 9♣", "9♠ 7♣"), Some(Odds::to_one(4)));
+        assert_eq!(pays(&bet, "K♠ 9♠", "9♠ 7♣"), None);
+    }
+
+    #[test]
+    fn royal_match_pays_suited_cards() {
+        let bet = RoyalMatch::default();
+        assert_eq!(pays(&bet, "Q♦ K♦", "2♣"), Some(Odds::to_one(25)));
+        assert_eq!(pays(&bet, "2♦ 9♦", "2♣"), Some(Odds { win: 5, per: 2 }));
+        assert_eq!(pays(&bet, "K♦ Q♠", "2♣"), None);
+    }
+
+    #[test]
+    fn thirteen_loses_over_and_under() {
+        let (over, under) = (OverUnder13::over(), OverUnder13::under());
+        assert_eq!(pays(&over, "K♠ 5♥", "2♣"), Some(Odds::to_one(1)));
+        assert_eq!(pays(&under, "K♠ 5♥", "2♣"), None);
+        // Aces count as one
+        assert_eq!(pays(&under, "A♠ Q♥", "2♣"), Some(Odds::to_one(1)));
+        assert_eq!(pays(&over, "A♠ Q♥", "2♣"), None);
+        assert_eq!(pays(&over, "K♠ 3♥", "2♣"), None);
+        assert_eq!(pays(&under, "K♠ 3♥", "2♣"), None);
+    }
+
+    #[test]
+    fn buster_pays_by_the_cards_in_the_dealers_bust() {
+        let bet = Buster::default();
+        assert_eq!(pays(&bet, "2♣ 2♥", "6♠ T♥ K♦"), Some(Odds::to_one(1)));
+        // Aces count as one once the dealer is past 21
+        assert_eq!(pays(&bet, "2♣ 2♥", "A♠ 6♥ 5♦ K♣"), Some(Odds::to_one(2)));
+        assert_eq!(pays(&bet, "2♣ 2♥", "2♠ 3♥ 4♦ 5♣ 9♠"), Some(Odds::to_one(4)));
+        assert_eq!(pays(&bet, "2♣ 2♥", "2♠ 2♥ 2♦ 3♣ 5♠ 9♥"), Some(Odds::to_one(18)));
+        assert_eq!(pays(&bet, "2♣ 2♥", "2♠ 2♥ 2♦ 2♣ 3♠ 3♥ 9♦"), Some(Odds::to_one(50)));
+        assert_eq!(pays(&bet, "2♣ 2♥", "2♠ 2♥ 2♦ 2♣ 3♠ 3♥ 3♦ 6♣"), Some(Odds::to_one(250)));
+        assert_eq!(pays(&bet, "2♣ 2♥", "6♠ 5♥ K♦"), None);
+    }
+}
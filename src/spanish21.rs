@@ -0,0 +1,125 @@
+use crate::{
+    strategy::BasicStrategy,
+    types::{Card, CardFace, Hand, PlayerChoice, PlayerChoices},
+};
+
+/// Basic strategy for Spanish 21 with six decks, dealer hits soft 17
+///
+/// Without the ten-rank number cards doubling is weaker and stiff hands are hit more often.
+/// Standing also depends on how many cards are in the hand, since hitting to a 21 of five
+/// or more cards earns a bonus.
+pub struct Spanish21Strategy;
+
+impl Spanish21Strategy {
+    pub fn choice(hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+        let value = hand.value();
+        let cards = hand.cards.len();
+        let dealer_value = dealer_card.face_value();
+        let is_soft = hand.is_soft();
+
+        if choices.contains(PlayerChoices::SURRENDER) && Self::surrender(hand, dealer_card) {
+            return PlayerChoice::Surrender;
+        }
+        if choices.contains(PlayerChoices::SPLIT) {
+            if let Some(card) = hand.splitable_card() {
+                if Self::split(&card, dealer_value) {
+                    return PlayerChoice::Split;
+                }
+            }
+        }
+
+        let double = if is_soft {
+            Self::soft_double(value, dealer_value, cards)
+        } else {
+            Self::hard_double(value, dealer_value, cards)
+        };
+        if double && choices.contains(PlayerChoices::DOUBLE) {
+            return PlayerChoice::Double; // Doubling again works off the same chart
+        }
+        if !choices.contains(PlayerChoices::HIT) {
+            // A doubled hand can only be stood or rescued from here
+            if choices.contains(PlayerChoices::RESCUE) && BasicStrategy::rescue(hand, dealer_card) {
+                return PlayerChoice::Rescue;
+            }
+            return PlayerChoice::Stand;
+        }
+
+        let stand = if is_soft {
+            Self::soft_stand(value, dealer_value)
+        } else {
+            Self::hard_stand(value, dealer_value, cards)
+        };
+        if stand {
+            PlayerChoice::Stand
+        } else {
+            PlayerChoice::Hit
+        }
+    }
+
+    /// Late surrender of the first two cards, only ever against an ace
+    pub fn surrender(hand: &Hand, dealer_card: &Card) -> bool {
+        if hand.is_soft() || hand.cards.len() != 2 {
+            return false;
+        }
+        dealer_card.face_value() == 11 && (15..=17).contains(&hand.value())
+    }
+
+    fn split(card: &CardFace, dealer_value: u8) -> bool {
+        match card {
+            CardFace::Ace => true,
+            CardFace::Number(9) => matches!(dealer_value, 3..=6 | 8..=9),
+            CardFace::Number(8) => true,
+            CardFace::Number(7) => dealer_value <= 7,
+            CardFace::Number(6) => (4..=6).contains(&dealer_value),
+            CardFace::Number(3) => (4..=7).contains(&dealer_value),
+            CardFace::Number(2) => (3..=7).contains(&dealer_value),
+            _ => false, // Tens, fives and fours are played as totals
+        }
+    }
+
+    fn hard_double(value: u8, dealer_value: u8, cards: usize) -> bool {
+        match value {
+            9 => dealer_value == 6 && cards <= 3,
+            10 => dealer_value <= 7 && cards <= 3,
+            11 => cards <= 3 || (cards == 4 && dealer_value <= 6),
+            _ => false,
+        }
+    }
+
+    fn soft_double(value: u8, dealer_value: u8, cards: usize) -> bool {
+        if cards > 3 {
+            return false;
+        }
+        match value {
+            13..=14 => dealer_value == 6,
+            15..=16 => (5..=6).contains(&dealer_value),
+            17..=18 => (4..=6).contains(&dealer_value),
+            _ => false,
+        }
+    }
+
+    fn soft_stand(value: u8, dealer_value: u8) -> bool {
+        match value {
+            19..=21 => true,
+            18 => dealer_value <= 8, // Hit soft 18 against 9, ten or ace
+            _ => false,
+        }
+    }
+
+    /// Stiff hands are hit with more cards to chase the five card 21 bonuses
+    fn hard_stand(value: u8, dealer_value: u8, cards: usize) -> bool {
+        match (value, dealer_value) {
+            (17..=21, _) => true,
+            (16, 2..=6) => cards <= 4,
+            (15, 2..=3) => cards <= 3,
+            (15, 4..=6) => cards <= 4,
+            (14, 2..=3) => cards <= 2,
+            (14, 4..=5) => cards <= 3,
+            (14, 6) => cards <= 4,
+            (13, 3..=4) => cards <= 2,
+            (13, 5..=6) => cards <= 3,
+            (12, 6) => cards <= 3,
+            _ => false,
+        }
+    }
+}
@@ -1,57 +1,137 @@
+use std::{fs::File, io::{self, Write}};
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
     game::Game,
-    types::{Card, PlayerChoice},
+    history::HandHistory,
+    money::Money,
+    stats::SessionStats,
+    strategy::PlayMode,
+    types::{Card, PlayerChoice, PlayerChoices},
 };
 
+#[derive(Serialize, Deserialize)]
 pub struct GameInPlay {
     pub game: Game,
     stage: Stage,
-    pub balance: u32,
-    original_bet: Option<u32>,
+    pub balance: Money,
+    pub stats: SessionStats,
+    /// Where every round is written once it's settled, if the session is being recorded. Only
+    /// the numbering of the shoes and rounds is saved with the session
+    pub history: Option<HandHistory>,
+}
+
+/// What [`GameInPlay::save`] writes
+#[derive(Serialize)]
+struct SavedSession<'a> {
+    game: &'a GameInPlay,
+    play_mode: &'a PlayMode,
+}
+
+/// What [`GameInPlay::load`] reads back
+#[derive(Deserialize)]
+struct LoadedSession {
+    game: GameInPlay,
+    play_mode: PlayMode,
 }
 
 impl GameInPlay {
-    pub fn new(game: Game, balance: u32) -> Self {
+    pub fn new(game: Game, balance: Money) -> Self {
         Self {
             game,
             stage: Stage::Start,
             balance,
-            original_bet: None,
+            stats: SessionStats::default(),
+            history: None,
         }
     }
 
+    /// Writes the whole session to `path` as JSON, shoe, count and whatever the strategy has
+    /// learned included, so it can be picked up exactly where it was left with
+    /// [`GameInPlay::load`]
+    pub fn save(&self, path: &str, play_mode: &PlayMode) -> io::Result<()> {
+        // Write a copy first so a crash part way through never leaves a broken save behind
+        let temp = format!("{}.tmp", path);
+        let mut out = io::BufWriter::new(File::create(&temp)?);
+        serde_json::to_writer(&mut out, &SavedSession { game: self, play_mode })?;
+        out.flush()?;
+        drop(out);
+        std::fs::rename(temp, path)
+    }
+
+    /// Picks up a session saved with [`GameInPlay::save`], along with the strategy it was played with
+    pub fn load(path: &str) -> io::Result<(Self, PlayMode)> {
+        let file = File::open(path)?;
+        let session: LoadedSession = serde_json::from_reader(io::BufReader::new(file))?;
+        Ok((session.game, session.play_mode))
+    }
+
     pub fn advance(&mut self) -> Option<InputNeeded> {
         if !self.game.has_started() {
             self.stage = Stage::Start;
+            if self.game.awaiting_cut() {
+                return Some(InputNeeded::Cut);
+            }
             return Some(InputNeeded::Bet);
         }
 
         if matches!(self.stage, Stage::HandOver) {
+            // Shuffle now if the cut card came out, so the new shoe is cut before betting
+            self.game.prepare_next_round();
             self.stage = Stage::AwaitBet;
+        }
+
+        if matches!(self.stage, Stage::AwaitBet) {
+            if self.game.awaiting_cut() {
+                return Some(InputNeeded::Cut);
+            }
             return Some(InputNeeded::Bet);
         }
 
+        if self.game.switch_offered() {
+            return Some(InputNeeded::Switch);
+        }
+        if self.game.early_surrender_offered() {
+            return Some(InputNeeded::EarlySurrender);
+        }
+
         if (self.game.dealer_has_blackjack() || self.game.player_has_blackjack())
             && !matches!(self.stage, Stage::Payout(_))
         {
             self.game.reveal_dealer_hand();
-            self.stage = Stage::Payout(vec![]);
+            // Without a hole card the dealer still draws to see if a player blackjack pushes
+            let cards = self.game.deal_dealer_second_card();
+            self.pay_out(cards);
             return None; // No input needed, just show results
         }
 
         if matches!(self.stage, Stage::DealerTurn) {
-            let cards = if !self.game.all_player_hands_busted() {
+            let cards = if self.game.dealer_needs_to_play() {
                 self.game.play_dealer_hand()
             } else {
-                vec![]
+                // A hand surrendered late without a hole card still loses to a blackjack
+                self.game.deal_dealer_second_card()
             };
-            self.stage = Stage::Payout(cards);
+            self.pay_out(cards);
             return None; // No input needed, dealer has played
         }
 
         if matches!(self.stage, Stage::Payout(_)) {
+            self.game.settle_side_bets();
             let payout = self.game.player_payout();
             self.balance += payout;
+            // Side bets are kept apart so they don't muddy the main game's results
+            let side_bets = self.game.side_bets();
+            self.stats.record_round(
+                self.game.player_total_bet() - self.game.side_bet_total(),
+                payout - self.game.side_bet_payout(),
+            );
+            self.stats.record_side_bets(side_bets);
+            if let Some(Err(error)) = self.history.as_mut().map(|history| history.round_over(&self.game)) {
+                eprintln!("Stopped writing the hand history: {}", error);
+                self.history = None;
+            }
             self.stage = Stage::HandOver;
             return Some(InputNeeded::HandOver);
         }
@@ -73,28 +153,70 @@ impl GameInPlay {
             println!("Second if statement dealer play");
             let cards = self.game.play_dealer_hand();
             // Dealer has played and the player has no more actions
-            self.stage = Stage::Payout(cards);
+            self.pay_out(cards);
             return None;
         }
 
         Some(InputNeeded::Choice)
     }
 
+    /// Moves on to the payout once the dealer has played out the hand for any side bets
+    /// waiting on it, so every card the dealer draws is shown
+    fn pay_out(&mut self, mut cards: Vec<Card>) {
+        cards.extend(self.game.play_dealer_for_side_bets());
+        self.stage = Stage::Payout(cards);
+    }
+
+    /// The plays the player can make on the current hand and afford with what's left of the
+    /// balance, a double the player is short for is made for less if the table allows it
+    pub fn player_choices(&self) -> PlayerChoices {
+        let mut choices = self.game.player_choices();
+        let can_double_for_less = self.game.config.player_can_double_for_less && self.balance > Money::ZERO;
+        if !self.game.free_double_offered() && self.balance < self.game.double_cost() && !can_double_for_less {
+            choices.remove(PlayerChoices::DOUBLE);
+        }
+        if self.balance == Money::ZERO {
+            choices.remove(PlayerChoices::DOUBLE_FOR_LESS);
+        }
+        if !self.game.free_split_offered() && self.balance < self.game.initial_wager() {
+            choices.remove(PlayerChoices::SPLIT);
+        }
+        choices
+    }
+
     pub fn player_move(&mut self, choice: PlayerChoice) {
-        if matches!(choice, PlayerChoice::Double | PlayerChoice::Split) {
-            self.original_bet = Some(self.original_bet.unwrap() * 2);
+        let choices = self.player_choices();
+        assert!(choices.contains(choice.into()), "{} is not allowed on the current hand.", choice);
+        // Free doubles and splits are put up by the casino, so they cost the player nothing
+        let free = match choice {
+            PlayerChoice::Double => self.game.free_double_offered(),
+            PlayerChoice::Split => self.game.free_split_offered(),
+            _ => false,
+        };
+        let choice = match choice {
+            PlayerChoice::Double if free => choice,
+            // A player short of money for a full double doubles for whatever is left, which
+            // the choices only allow if the table does
+            PlayerChoice::Double if self.balance < self.game.double_cost() => PlayerChoice::DoubleForLess(self.balance),
+            PlayerChoice::DoubleForLess(amount) => PlayerChoice::DoubleForLess(amount.min(self.balance)),
+            choice => choice,
+        };
+        if let Some(history) = self.history.as_mut() {
+            history.decision(&self.game, choice, choices);
         }
         self.game.take_turn(choice, &mut self.balance);
 
         self.stage = Stage::CheckWinConditions; // Check if the player has blackjack or if the dealer needs to play
     }
 
-    pub fn bet(&mut self, bet: u32) {
+    pub fn bet(&mut self, bet: Money) {
         assert!(
             self.stage.bet_needed(),
             "Bet is not needed in the current stage."
         );
-        self.original_bet = Some(bet);
+        if let Some(history) = self.history.as_mut() {
+            history.bet(&self.game, bet);
+        }
 
         match self.stage {
             Stage::Start => {
@@ -105,14 +227,36 @@ impl GameInPlay {
             }
             _ => unreachable!(),
         }
+        if self.game.shuffled_this_round() {
+            self.stats.record_shuffle();
+        }
         self.stage = Stage::CheckWinConditions; // Dealer or player could have blackjack
     }
 
+    /// Decide whether to swap the second cards of the two hands before they're played
+    pub fn switch(&mut self, switch: bool) {
+        if let Some(history) = self.history.as_mut() {
+            history.switch(switch);
+        }
+        self.game.switch(switch);
+    }
+
+    /// Answer the offer to surrender before the dealer checks for blackjack
+    pub fn early_surrender(&mut self, surrender: bool) {
+        if let Some(history) = self.history.as_mut() {
+            history.early_surrender(surrender);
+        }
+        self.game.early_surrender(surrender);
+    }
+
+    /// Cut the freshly shuffled shoe `position` cards from the top, or at a random spot for `None`
+    pub fn cut(&mut self, position: Option<usize>) {
+        self.game.cut_shoe(position);
+    }
+
     pub fn new_table(&mut self) {
         self.game = Game::new(self.game.config.clone());
-        self.balance = self.balance;
         self.stage = Stage::Start;
-        self.original_bet = None;
     }
 
     pub fn terminate(&mut self) {
@@ -128,19 +272,19 @@ impl std::fmt::Display for GameInPlay {
             }
             Stage::AwaitBet => {
                 writeln!(f, "{}", self.game)?;
-                writeln!(f, "Balance: ${}", self.balance)?;
+                writeln!(f, "Balance: {}", self.balance)?;
                 writeln!(f, "Please place your bet for the next hand.")
             }
             Stage::Exiting => {
                 writeln!(
                     f,
-                    "Thank you for playing! Your final balance is: ${}",
+                    "Thank you for playing! Your final balance is: {}",
                     self.balance
                 )
             }
             Stage::Payout(cards) => {
                 writeln!(f, "{}", self.game)?;
-                writeln!(f, "Balance: ${}", self.balance)?;
+                writeln!(f, "Balance: {}", self.balance)?;
                 for card in cards {
                     writeln!(f, "Dealer draws: {}", card)?;
                 }
@@ -151,35 +295,35 @@ impl std::fmt::Display for GameInPlay {
             }
             Stage::DealerTurn => {
                 writeln!(f, "{}", self.game)?;
-                writeln!(f, "Balance: ${}", self.balance)?;
+                writeln!(f, "Balance: {}", self.balance)?;
                 writeln!(f, "Dealer's turn is in progress. Please wait...")
             }
             Stage::CheckWinConditions => {
                 writeln!(f, "{}", self.game)?;
-                writeln!(f, "Balance: ${}", self.balance)
+                writeln!(f, "Balance: {}", self.balance)
             }
             Stage::HandOver => {
                 writeln!(f, "{}", self.game)?;
-                writeln!(f, "Balance: ${}", self.balance)?;
+                writeln!(f, "Balance: {}", self.balance)?;
                 let payout = self.game.player_payout();
                 let total_bet = self.game.player_total_bet();
                 if payout > total_bet {
                     writeln!(
                         f,
-                        "You won ${}! Your current balance is: ${}",
+                        "You won {}! Your current balance is: {}",
                         payout - total_bet,
                         self.balance
                     )?;
                 } else if payout < total_bet {
                     writeln!(
                         f,
-                        "You lost your bet of ${}. Your current balance is: ${}",
-                        total_bet, self.balance
+                        "You lost {}. Your current balance is: {}",
+                        total_bet - payout, self.balance
                     )?;
                 } else {
                     writeln!(
                         f,
-                        "It's a push! Your bet of ${} is returned to you. Current balance: ${}",
+                        "It's a push! Your bet of {} is returned to you. Current balance: {}",
                         total_bet, self.balance
                     )?;
                 }
@@ -190,6 +334,7 @@ impl std::fmt::Display for GameInPlay {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub enum Stage {
     /// The game hasn't made the initial bet yet
     Start,
@@ -215,7 +360,94 @@ impl Stage {
 }
 
 pub enum InputNeeded {
+    /// The shoe was shuffled and needs to be cut
+    Cut,
     Bet,
+    /// The player can swap the second cards of the two hands
+    Switch,
+    /// The player can surrender before the dealer checks for blackjack
+    EarlySurrender,
     Choice,
     HandOver,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::GameConfig, sequencing::AceSequencer, sidebets::Buster};
+
+    /// Bets $10 of the balance on a round dealt from the stacked cards and plays on to the
+    /// first decision
+    fn deal(config: GameConfig, balance: Money, notation: &str) -> GameInPlay {
+        let game = Game::stacked(config, notation).unwrap();
+        let mut game = GameInPlay::new(game, balance);
+        assert!(matches!(game.advance(), Some(InputNeeded::Bet)));
+        game.bet(Money::from_dollars(10));
+        while game.advance().is_none() {}
+        game
+    }
+
+    #[test]
+    #[should_panic(expected = "Split is not allowed on the current hand")]
+    fn rejects_a_play_the_hand_does_not_allow() {
+        let mut game = deal(GameConfig::default(), Money::from_dollars(100), "T♠ 7♥ | 8♦ 9♣");
+        game.player_move(PlayerChoice::Split);
+    }
+
+    #[test]
+    fn only_redoubles_what_the_balance_covers() {
+        let config = GameConfig { player_redoubles: 1, ..GameConfig::default() };
+        let mut game = deal(config.clone(), Money::from_dollars(25), "T♠ 7♥ | 2♦ 3♣ | 4♠ 2♥");
+        game.player_move(PlayerChoice::Double);
+        while game.advance().is_none() {}
+        assert_eq!(game.balance, Money::from_dollars(5));
+        assert!(game.game.player_choices().contains(PlayerChoices::DOUBLE));
+        assert!(!game.player_choices().contains(PlayerChoices::DOUBLE));
+
+        let config = GameConfig { player_can_double_for_less: true, ..config };
+        let mut game = deal(config, Money::from_dollars(25), "T♠ 7♥ | 2♦ 3♣ | 4♠ 2♥");
+        game.player_move(PlayerChoice::Double);
+        while game.advance().is_none() {}
+        game.player_move(PlayerChoice::Double);
+        assert_eq!(game.balance, Money::ZERO);
+        assert_eq!(game.game.player_bets(), &[Money::from_dollars(25)]);
+    }
+
+    #[test]
+    fn shows_the_cards_the_dealer_draws_for_side_bets() {
+        let mut config = GameConfig::default();
+        config.side_bets.push(Box::new(Buster::default()));
+        let mut game = deal(config, Money::from_dollars(100), "T♠ 6♥ | T♦ 5♣ | 9♠ 7♦");
+        game.player_move(PlayerChoice::Hit);
+        while !matches!(game.stage, Stage::Payout(_)) {
+            assert!(game.advance().is_none());
+        }
+        let Stage::Payout(cards) = &game.stage else { unreachable!() };
+        assert_eq!(cards.len(), 1);
+        assert!(cards[0].is_same_card(&"7♦".parse().unwrap()));
+    }
+
+    #[test]
+    fn resumes_the_strategy_and_history_numbering() {
+        let mut game = GameInPlay::new(Game::stacked(GameConfig::default(), "T♠ 7♥ | 8♦ 9♣").unwrap(), Money::from_dollars(100));
+        game.history = Some(HandHistory::new(Box::new(io::sink())));
+        assert!(matches!(game.advance(), Some(InputNeeded::Bet)));
+        game.bet(Money::from_dollars(10));
+        while game.advance().is_none() {}
+        game.player_move(PlayerChoice::Stand);
+        while !matches!(game.advance(), Some(InputNeeded::Bet)) {}
+        let mut sequencer = AceSequencer::default();
+        sequencer.hand_over(&game.game);
+        let play_mode = PlayMode::AceSequencing(sequencer);
+
+        let path = std::env::temp_dir().join(format!("resume-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        game.save(path, &play_mode).unwrap();
+        let (resumed, resumed_mode) = GameInPlay::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(serde_json::to_value(&resumed_mode).unwrap(), serde_json::to_value(&play_mode).unwrap());
+        assert_eq!(serde_json::to_value(&resumed.history).unwrap(), serde_json::to_value(&game.history).unwrap());
+        assert_eq!(serde_json::to_value(&resumed.history).unwrap()["round"], 1);
+    }
+}
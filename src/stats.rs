@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{money::{Money, Net}, sidebets::SideBetResult};
+
+/// Rough timing of a heads up game used to estimate the pace of play
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TableTiming {
+    /// Seconds to deal and settle one round
+    pub seconds_per_round: f64,
+    /// Seconds the dealer spends shuffling a shoe by hand
+    pub seconds_per_shuffle: f64,
+}
+
+impl Default for TableTiming {
+    fn default() -> Self {
+        Self {
+            // Heads up play manages around 200 rounds an hour
+            seconds_per_round: 18.0,
+            // A six deck hand shuffle takes a minute or two
+            seconds_per_shuffle: 90.0,
+        }
+    }
+}
+
+/// Results collected over a session
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct SessionStats {
+    /// Number of rounds played
+    pub rounds: usize,
+    /// Number of times the dealer had to stop and shuffle
+    pub shuffles: usize,
+    /// Total amount put into action, including doubles and splits
+    pub total_wagered: Money,
+    /// Net winnings of the player in cents
+    pub net: i64,
+    /// Results of each kind of side bet, kept apart from the main game
+    pub side_bets: Vec<SideBetStats>,
+
+    pub timing: TableTiming,
+}
+
+impl SessionStats {
+    pub fn record_round(&mut self, wagered: Money, returned: Money) {
+        self.rounds += 1;
+        self.total_wagered += wagered;
+        self.net += returned.net_of(wagered);
+    }
+
+    pub fn record_side_bets(&mut self, results: &[SideBetResult]) {
+        for result in results {
+            let index = match self.side_bets.iter().position(|stats| stats.name == result.name) {
+                Some(index) => index,
+                None => {
+                    self.side_bets.push(SideBetStats { name: result.name.to_string(), ..Default::default() });
+                    self.side_bets.len() - 1
+                }
+            };
+            self.side_bets[index].record(result);
+        }
+    }
+
+    pub fn record_shuffle(&mut self) {
+        self.shuffles += 1;
+    }
+
+    /// Expected value per unit wagered
+    pub fn expected_value(&self) -> f64 {
+        if self.total_wagered == Money::ZERO {
+            return 0.0;
+        }
+        self.net as f64 / self.total_wagered.cents() as f64
+    }
+
+    /// Estimated rounds dealt per hour including the time lost to shuffling
+    pub fn rounds_per_hour(&self) -> f64 {
+        let seconds = self.rounds as f64 * self.timing.seconds_per_round
+            + self.shuffles as f64 * self.timing.seconds_per_shuffle;
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        self.rounds as f64 * 3600.0 / seconds
+    }
+
+    /// Expected winnings in dollars per hour at the estimated pace of play
+    pub fn hourly_win(&self) -> f64 {
+        if self.rounds == 0 {
+            return 0.0;
+        }
+        self.net as f64 / 100.0 / self.rounds as f64 * self.rounds_per_hour()
+    }
+}
+
+/// Results of one kind of side bet over a session
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct SideBetStats {
+    pub name: String,
+    /// Number of bets placed
+    pub bets: usize,
+    pub total_wagered: Money,
+    /// Net result in cents
+    pub net: i64,
+    /// Amount wagered and net result in cents at each true count the bet was placed at
+    pub by_true_count: BTreeMap<isize, (Money, i64)>,
+}
+
+impl SideBetStats {
+    pub fn record(&mut self, result: &SideBetResult) {
+        let net = result.returned().net_of(result.wager);
+        self.bets += 1;
+        self.total_wagered += result.wager;
+        self.net += net;
+        let at_count = self.by_true_count.entry(result.true_count).or_default();
+        at_count.0 += result.wager;
+        at_count.1 += net;
+    }
+
+    /// Expected value per unit wagered
+    pub fn expected_value(&self) -> f64 {
+        if self.total_wagered == Money::ZERO {
+            return 0.0;
+        }
+        self.net as f64 / self.total_wagered.cents() as f64
+    }
+
+    /// The lowest true count where only betting at that count or higher would have come out
+    /// ahead, `None` if no count would
+    pub fn positive_from(&self) -> Option<isize> {
+        let mut net = 0;
+        let mut positive_from = None;
+        for (&true_count, &(_, at_count)) in self.by_true_count.iter().rev() {
+            net += at_count;
+            if net > 0 {
+                positive_from = Some(true_count);
+            }
+        }
+        positive_from
+    }
+}
+
+impl std::fmt::Display for SideBetStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: Bets: {}, Wagered: {}, Net: {}, EV: {:.2}%",
+            self.name,
+            self.bets,
+            self.total_wagered,
+            Net(self.net),
+            self.expected_value() * 100.0
+        )?;
+        match self.positive_from() {
+            Some(true_count) => write!(f, ", Positive from true count {:+}", true_count),
+            None => write!(f, ", Never positive"),
+        }
+    }
+}
+
+impl std::fmt::Display for SessionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Rounds: {}, Shuffles: {}, Wagered: {}, Net: {}", self.rounds, self.shuffles, self.total_wagered, Net(self.net))?;
+        write!(
+            f,
+            "EV: {:.2}%, Rounds/hour: {:.0}, Win/hour: ${:.2}",
+            self.expected_value() * 100.0,
+            self.rounds_per_hour(),
+            self.hourly_win()
+        )?;
+        for side_bet in &self.side_bets {
+            write!(f, "\n{}", side_bet)?;
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,20 @@
-use crate::{input::{get_player_bet, get_player_choice, wait_for_player_input}, types::{Card, CardFace, Hand, PlayerChoice, PlayerChoices}};
+use serde::{Deserialize, Serialize};
 
-const BASE_BET: u32 = 50; // Base bet size for basic strategy
+use crate::{config::GameVariant, doubleexposure::DoubleExposureStrategy, freebet::FreeBetStrategy, game::Game, holecarding::HoleCarder, input::{get_early_surrender, get_player_bet, get_player_choice, get_player_cut, get_switch, wait_for_player_input}, money::Money, sequencing::AceSequencer, spanish21::Spanish21Strategy, switch::SwitchStrategy, tracking::ShuffleTracker, types::{Card, CardFace, Hand, PlayerChoice, PlayerChoices}};
 
+pub(crate) const BASE_BET: Money = Money::from_dollars(50); // Base bet size for basic strategy
+
+/// How the player's decisions are made, along with anything the strategy learned so far
+#[derive(Serialize, Deserialize)]
 pub enum PlayMode {
     Interactive,
     Basic,
     Counting,
     CountingConservative,
     CountingAdvanced,
+    ShuffleTracking(ShuffleTracker),
+    AceSequencing(AceSequencer),
+    HoleCarding(HoleCarder),
 }
 
 impl PlayMode {
@@ -20,34 +27,100 @@ impl PlayMode {
             PlayMode::CountingConservative
         } else if args.contains(&String::from("-ca")) {
             PlayMode::CountingAdvanced
+        } else if args.contains(&String::from("-t")) {
+            PlayMode::ShuffleTracking(ShuffleTracker::default())
+        } else if args.contains(&String::from("-s")) {
+            PlayMode::AceSequencing(AceSequencer::default())
+        } else if args.contains(&String::from("-hc")) {
+            PlayMode::HoleCarding(HoleCarder::default())
         } else {
             PlayMode::Interactive
         }
     }
-    pub fn bet(&self, balance: u32, true_count: isize) -> u32 {
+    /// Where to cut a freshly shuffled shoe, `None` lets the dealer pick a random spot
+    pub fn cut(&mut self, game: &Game) -> Option<usize> {
+        match self {
+            PlayMode::Interactive => get_player_cut(game.cards_left().unwrap_or_default()),
+            PlayMode::ShuffleTracking(tracker) => tracker.cut(game),
+            PlayMode::AceSequencing(sequencer) => sequencer.cut(game),
+            _ => None,
+        }
+    }
+    pub fn bet(&mut self, balance: Money, game: &Game) -> Money {
+        let true_count = game.true_count();
         match self {
             PlayMode::Interactive => get_player_bet(),
-            PlayMode::Basic => 100, // Fixed bet for basic strategy
+            PlayMode::Basic => Money::from_dollars(100), // Fixed bet for basic strategy
             PlayMode::Counting => simple_counting_bet(true_count),
             PlayMode::CountingConservative => counting_bet_size(balance, true_count),
             PlayMode::CountingAdvanced => kelly_bet_size(balance, true_count),
+            PlayMode::ShuffleTracking(tracker) => tracker.bet(game),
+            PlayMode::AceSequencing(sequencer) => sequencer.bet(game),
+            PlayMode::HoleCarding(carder) => carder.bet(),
         }
     }
-    pub fn choice(&self, choices: PlayerChoices, hand: &Hand, dealer_card: &Card, true_count: isize) -> PlayerChoice {
+    pub fn choice(&mut self, choices: PlayerChoices, hand: &Hand, dealer_card: &Card, true_count: isize, game: &Game) -> PlayerChoice {
         match self {
             PlayMode::Interactive => get_player_choice(choices),
-            PlayMode::Basic => {
-                let choice = BasicStrategy::choice(hand, dealer_card, choices);
+            PlayMode::Basic | PlayMode::AceSequencing(_) => {
+                let choice = basic_choice(game, hand, dealer_card, choices);
                 println!("Basic strategy suggests: {}", choice);
                 wait_for_player_input(true);
                 choice
             }
-            PlayMode::Counting | PlayMode::CountingAdvanced | PlayMode::CountingConservative => {
-                let choice= CountingStrategy::choice(hand, dealer_card, choices, true_count);
+            PlayMode::Counting | PlayMode::CountingAdvanced | PlayMode::CountingConservative | PlayMode::ShuffleTracking(_) => {
+                let mut choice = counting_choice(game, hand, dealer_card, choices, true_count);
+                if matches!(self, PlayMode::CountingConservative) {
+                    choice = conservative_double(game, hand, dealer_card, choices, choice);
+                }
                 println!("Counting strategy suggests: {}", choice);
                 wait_for_player_input(true);
                 choice
             }
+            PlayMode::HoleCarding(carder) => {
+                let choice = carder.choice(game, hand, dealer_card, choices);
+                println!("Hole card strategy suggests: {}", choice);
+                wait_for_player_input(true);
+                choice
+            }
+        }
+    }
+    /// Whether to swap the second cards of the two hands in Blackjack Switch
+    pub fn switch(&mut self, game: &Game) -> bool {
+        match self {
+            PlayMode::Interactive => get_switch(),
+            _ => SwitchStrategy::switch(game),
+        }
+    }
+    /// Whether to take the offer to surrender before the dealer checks for blackjack
+    pub fn early_surrender(&mut self, game: &Game) -> bool {
+        let hand = &game.player_hands()[0];
+        let dealer_card = game.dealer_up_card();
+        match self {
+            PlayMode::Interactive => get_early_surrender(),
+            PlayMode::Basic | PlayMode::AceSequencing(_) => BasicStrategy::surrender(hand, &dealer_card, true),
+            PlayMode::Counting | PlayMode::CountingAdvanced | PlayMode::CountingConservative | PlayMode::ShuffleTracking(_) => {
+                CountingStrategy::surrender(hand, &dealer_card, true, game.true_count())
+            }
+            PlayMode::HoleCarding(carder) => carder.early_surrender(game, hand, &dealer_card),
+        }
+    }
+    /// Lets strategies that keep records see how the round was settled
+    pub fn hand_over(&mut self, game: &Game) {
+        match self {
+            PlayMode::ShuffleTracking(tracker) => tracker.hand_over(game),
+            PlayMode::AceSequencing(sequencer) => sequencer.hand_over(game),
+            PlayMode::HoleCarding(carder) => carder.hand_over(game),
+            _ => {}
+        }
+    }
+    /// Anything the strategy has to report on the session so far
+    pub fn report(&self) -> Option<String> {
+        match self {
+            PlayMode::ShuffleTracking(tracker) => Some(tracker.report.to_string()),
+            PlayMode::AceSequencing(sequencer) => Some(sequencer.to_string()),
+            PlayMode::HoleCarding(carder) => Some(carder.report.to_string()),
+            _ => None,
         }
     }
     pub fn wait_for_player_input(&self) {
@@ -61,11 +134,98 @@ impl PlayMode {
     }
 }
 
+/// The play basic strategy makes, or the variant's own strategy if it has one
+pub fn basic_choice(game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+    if let Some(choice) = variant_choice(game, hand, dealer_card, choices) {
+        choice
+    } else if choices.contains(PlayerChoices::SURRENDER)
+        && BasicStrategy::surrender(hand, dealer_card, game.surrendering_early())
+    {
+        PlayerChoice::Surrender
+    } else {
+        BasicStrategy::choice(hand, dealer_card, choices)
+    }
+}
+
+/// The play the counting strategy makes at `true_count`, or the variant's own strategy if
+/// it has one
+pub fn counting_choice(game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices, true_count: isize) -> PlayerChoice {
+    // There are no count indices for Spanish 21, so the count only moves the bet
+    if let Some(choice) = variant_choice(game, hand, dealer_card, choices) {
+        choice
+    } else if choices.contains(PlayerChoices::SURRENDER)
+        && CountingStrategy::surrender(hand, dealer_card, game.surrendering_early(), true_count)
+    {
+        PlayerChoice::Surrender
+    } else {
+        CountingStrategy::choice(hand, dealer_card, choices, true_count)
+    }
+}
+
+/// The conservative counter only puts up half the wager on the soft doubles against a 2, 3
+/// or 4 when the table allows doubling for less. Those doubles win little over hitting, so
+/// halving them gives up a sliver of the edge for much less of the bankroll at risk.
+fn conservative_double(game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices, choice: PlayerChoice) -> PlayerChoice {
+    let marginal = hand.is_soft() && (2..=4).contains(&dealer_card.face_value());
+    if matches!(choice, PlayerChoice::Double) && marginal && choices.contains(PlayerChoices::DOUBLE_FOR_LESS) {
+        PlayerChoice::DoubleForLess(game.initial_wager().fraction(0.5))
+    } else {
+        choice
+    }
+}
+
+/// The strategy for a variant whose rules are too far from the standard game for the
+/// basic or counting charts, None for the standard game
+fn variant_choice(game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> Option<PlayerChoice> {
+    match game.config.variant {
+        GameVariant::Standard => None,
+        GameVariant::Spanish21 => Some(Spanish21Strategy::choice(hand, dealer_card, choices)),
+        GameVariant::BlackjackSwitch => Some(SwitchStrategy::choice(game, hand, dealer_card, choices)),
+        GameVariant::FreeBet => Some(FreeBetStrategy::choice(game, hand, dealer_card, choices)),
+        GameVariant::DoubleExposure => Some(DoubleExposureStrategy::choice(game, hand, dealer_card, choices)),
+    }
+}
+
 pub struct BasicStrategy;
 
 impl BasicStrategy {
+    /// Whether to surrender the first two cards
+    ///
+    /// `early` is true when the dealer could still have blackjack and surrendering returns
+    /// half the bet anyway, which makes surrendering against an ace or ten much stronger.
+    pub fn surrender(hand: &Hand, dealer_card: &Card, early: bool) -> bool {
+        if hand.is_soft() || hand.cards.len() != 2 {
+            return false;
+        }
+        let value = hand.value();
+        let eights = hand.splitable_card() == Some(CardFace::Number(8));
+        match (early, dealer_card.face_value(), value) {
+            (true, 11, 5..=7 | 12..=17) => true, // Early surrender everything weak against an ace
+            (true, 10, 14..=16) => true, // Early surrender 14-16 against a ten, eights included
+            (true, 9, 16) => !eights,
+            (false, 9..=11, 16) => !eights, // Late surrender hard 16 against 9, 10, or Ace but split eights
+            (false, 10, 15) => true, // Late surrender hard 15 against a ten
+            _ => false,
+        }
+    }
+
+
+    /// Whether to give up a doubled hand, standing is worth less than losing the original bet
+    pub fn rescue(hand: &Hand, dealer_card: &Card) -> bool {
+        hand.value() <= 16 && dealer_card.face_value() >= 8
+    }
 
+    /// The chart's play, standing when the chart calls for a play the hand doesn't allow,
+    /// such as hitting split aces or a doubled hand
     pub fn choice(hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+        let choice = Self::chart_choice(hand, dealer_card, choices);
+        if choices.contains(choice.into()) { choice } else { PlayerChoice::Stand }
+    }
+
+    fn chart_choice(hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+        if choices.contains(PlayerChoices::RESCUE) {
+            return if Self::rescue(hand, dealer_card) { PlayerChoice::Rescue } else { PlayerChoice::Stand };
+        }
         let value = hand.value();
         let splitable_card = if choices.contains(PlayerChoices::SPLIT) {
             hand.splitable_card()
@@ -83,9 +243,6 @@ impl BasicStrategy {
             (_, _, _, Some(Ace)) => {
                 PlayerChoice::Split // Always split aces
             }
-            (_, _, _, Some(Face(_))) => {
-                PlayerChoice::Stand // Never split face cards
-            }
             (_, _, _, Some(Number(10))) => {
                 PlayerChoice::Stand // Never split 10s
             }
@@ -118,7 +275,7 @@ impl BasicStrategy {
                 }
             }
             (d, _, _, Some(Number(5))) => {
-                if d <= 9 {
+                if choices.contains(PlayerChoices::DOUBLE) && d <= 9 {
                     PlayerChoice::Double // Double on 5s against dealer 2-9
                 } else {
                     PlayerChoice::Hit // Hit on 5s against dealer 10 or Ace
@@ -152,8 +309,8 @@ impl BasicStrategy {
             (d, 18, true, _) => {
                 if d >= 9 {
                     PlayerChoice::Hit
-                } else if d == 7 || d == 8 || d == 2 {
-                    PlayerChoice::Stand // Stand on soft 18 against dealer 7 or 8
+                } else if d == 7 || d == 8 || d == 2 || !choices.contains(PlayerChoices::DOUBLE) {
+                    PlayerChoice::Stand // Stand on soft 18 against dealer 7 or 8, or when double not allowed
                 } else {
                     PlayerChoice::Double // Double on soft 18 against dealer 3-6
                 }
@@ -179,9 +336,9 @@ impl BasicStrategy {
                     PlayerChoice::Hit // Hit if double not allowed
                 }
             }
-            // Unreachable soft totals
+            // Soft 12 is a pair of aces that can't be split again
             (_, p, true, _) if p <= 12 => {
-                unreachable!("Soft total below 13")
+                PlayerChoice::Hit
             }
 
             // Hard totals
@@ -234,7 +391,31 @@ impl BasicStrategy {
 pub struct CountingStrategy;
 
 impl CountingStrategy {
+    /// Whether to surrender the first two cards, late surrender follows the Fab 4 indices
+    pub fn surrender(hand: &Hand, dealer_card: &Card, early: bool, true_count: isize) -> bool {
+        if early || hand.is_soft() || hand.cards.len() != 2 {
+            return BasicStrategy::surrender(hand, dealer_card, early);
+        }
+        match (dealer_card.face_value(), hand.value(), true_count) {
+            (10, 14, tc) => tc >= 3, // Surrender 14 against a ten at TC >= +3
+            (10, 15, tc) => tc >= 0, // Surrender 15 against a ten at TC >= 0
+            (9, 15, tc) => tc >= 2, // Surrender 15 against a 9 at TC >= +2
+            (11, 15, tc) => tc >= 1, // Surrender 15 against an ace at TC >= +1
+            _ => BasicStrategy::surrender(hand, dealer_card, early),
+        }
+    }
+
+    /// The chart's play at `true_count`, standing when the chart calls for a play the hand
+    /// doesn't allow
     pub fn choice(hand: &Hand, dealer_card: &Card, choices: PlayerChoices, true_count: isize) -> PlayerChoice {
+        let choice = Self::chart_choice(hand, dealer_card, choices, true_count);
+        if choices.contains(choice.into()) { choice } else { PlayerChoice::Stand }
+    }
+
+    fn chart_choice(hand: &Hand, dealer_card: &Card, choices: PlayerChoices, true_count: isize) -> PlayerChoice {
+        if choices.contains(PlayerChoices::RESCUE) {
+            return if BasicStrategy::rescue(hand, dealer_card) { PlayerChoice::Rescue } else { PlayerChoice::Stand };
+        }
         let value = hand.value();
         let splitable_card = if choices.contains(PlayerChoices::SPLIT) {
             hand.splitable_card()
@@ -252,9 +433,6 @@ impl CountingStrategy {
             (_, _, _, Some(Ace), _) => {
                 PlayerChoice::Split // Always split aces
             }
-            (_, _, _, Some(Face(_)), _) => {
-                PlayerChoice::Stand // Never split face cards
-            }
             // COUNT ADJUSTMENT: Split 10s in very high counts
             (6, _, _, Some(Number(10)), tc) if tc >= 4 => {
                 PlayerChoice::Split // Split 10s vs 6 when TC >= +4
@@ -294,7 +472,7 @@ impl CountingStrategy {
                 }
             }
             (d, _, _, Some(Number(5)), _) => {
-                if d <= 9 {
+                if choices.contains(PlayerChoices::DOUBLE) && d <= 9 {
                     PlayerChoice::Double
                 } else {
                     PlayerChoice::Hit
@@ -330,7 +508,7 @@ impl CountingStrategy {
             (d, 18, true, _, _) => {
                 if d >= 9 {
                     PlayerChoice::Hit
-                } else if d == 7 || d == 8 || d == 2 {
+                } else if d == 7 || d == 8 || d == 2 || !choices.contains(PlayerChoices::DOUBLE) {
                     PlayerChoice::Stand
                 } else {
                     PlayerChoice::Double
@@ -358,7 +536,7 @@ impl CountingStrategy {
                 }
             }
             (_, p, true, _, _) if p <= 12 => {
-                unreachable!("Soft total below 13")
+                PlayerChoice::Hit
             }
 
             // COUNT-ADJUSTED HARD TOTALS
@@ -445,9 +623,9 @@ impl CountingStrategy {
     }
 }
 
-fn counting_bet_size(balance: u32, true_count: isize) -> u32 {
+fn counting_bet_size(balance: Money, true_count: isize) -> Money {
     // Conservative bankroll management: never bet more than 2% of balance
-    let max_bet = (balance as f32 * 0.02) as u32;
+    let max_bet = balance.fraction(0.02);
     
     // Base betting unit (minimum bet)
     let base_unit = BASE_BET; // $25 base unit
@@ -457,7 +635,7 @@ fn counting_bet_size(balance: u32, true_count: isize) -> u32 {
     
     let bet_size = match true_count {
         // Negative counts: leave the table
-        tc if tc <= -1 => 0, // Leave when count is negative
+        tc if tc <= -1 => Money::ZERO, // Leave when count is negative
         
         // Neutral count: minimum bet
         0 => base_unit, // Minimum bet when neutral
@@ -477,16 +655,16 @@ fn counting_bet_size(balance: u32, true_count: isize) -> u32 {
     };
     
     // Never bet more than we can afford (unless leaving table)
-    if bet_size == 0 { 0 } else { bet_size.min(max_affordable) }
+    if bet_size == Money::ZERO { Money::ZERO } else { bet_size.min(max_affordable) }
 }
 
 // Alternative: More aggressive Kelly Criterion approach
-fn kelly_bet_size(balance: u32, true_count: isize) -> u32 {
-    let base_unit = 50; // Larger base unit for Kelly
+fn kelly_bet_size(balance: Money, true_count: isize) -> Money {
+    let base_unit = Money::from_dollars(50); // Larger base unit for Kelly
     
     // Leave table when count is negative
     if true_count <= -1 {
-        return 0;
+        return Money::ZERO;
     }
     
     // Kelly formula approximation for blackjack
@@ -507,19 +685,19 @@ fn kelly_bet_size(balance: u32, true_count: isize) -> u32 {
     
     // Kelly fraction
     let kelly_fraction = player_advantage / 2.0;
-    let kelly_bet = (balance as f32 * kelly_fraction) as u32;
+    let kelly_bet = balance.fraction(kelly_fraction);
     
     // Cap between base unit and reasonable maximum
-    let max_bet = balance / 20; // Never more than 5% of bankroll
+    let max_bet = balance.times(1, 20); // Never more than 5% of bankroll
     kelly_bet.clamp(base_unit, max_bet)
 }
 
 // Simplified approach for beginners
-fn simple_counting_bet(true_count: isize) -> u32 {
+fn simple_counting_bet(true_count: isize) -> Money {
     let base_bet = BASE_BET;
     
     match true_count {
-        tc if tc <= -1 => 0,                 // Leave table when negative
+        tc if tc <= -1 => Money::ZERO,       // Leave table when negative
         0 => base_bet,                       // 1 unit at neutral
         1 => base_bet * 2,                   // 2 units  
         2 => base_bet * 4,                   // 4 units
@@ -527,4 +705,43 @@ fn simple_counting_bet(true_count: isize) -> u32 {
         tc if tc >= 4 => base_bet * 8,       // 8 units max
         _ => base_bet,
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+
+    fn hand(notation: &str) -> Hand {
+        let mut hand = Hand::new(false);
+        for card in notation.split_whitespace() {
+            hand.push(card.parse().unwrap());
+        }
+        hand
+    }
+
+    #[test]
+    fn stands_when_the_chart_play_is_not_allowed() {
+        let dealer_card: Card = "T♠".parse().unwrap();
+        // A doubled hard 16 can only be stood or doubled again
+        let doubled = hand("9♦ 2♣ 5♥");
+        let choices = PlayerChoices::STAND | PlayerChoices::DOUBLE;
+        assert!(matches!(BasicStrategy::choice(&doubled, &dealer_card, choices), PlayerChoice::Stand));
+        assert!(matches!(CountingStrategy::choice(&doubled, &dealer_card, choices, 0), PlayerChoice::Stand));
+        assert!(matches!(BasicStrategy::choice(&doubled, &dealer_card, PlayerChoices::HIT | choices), PlayerChoice::Hit));
+    }
+
+    #[test]
+    fn conservative_counter_doubles_soft_hands_for_less_against_weak_cards() {
+        let config = GameConfig { player_can_double_for_less: true, ..GameConfig::default() };
+        let mut game = Game::stacked(config, "4♠ 7♥ | A♦ 6♣").unwrap();
+        let mut balance = Money::from_dollars(100);
+        game.start_game(Money::from_dollars(10), &mut balance);
+        let choices = game.player_choices();
+        let soft_17 = game.player_current_hand();
+        let choice = conservative_double(&game, &soft_17, &game.dealer_up_card(), choices, PlayerChoice::Double);
+        assert!(matches!(choice, PlayerChoice::DoubleForLess(amount) if amount == Money::from_dollars(5)));
+        let hard_11 = hand("5♦ 6♣");
+        let choice = conservative_double(&game, &hard_11, &game.dealer_up_card(), choices, PlayerChoice::Double);
+        assert!(matches!(choice, PlayerChoice::Double));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    game::Game,
+    holecarding::player_odds,
+    strategy::BasicStrategy,
+    types::{Card, Hand, PlayerChoice, PlayerChoices},
+};
+
+/// Plays Blackjack Switch by working out every play from the cards left in the shoe
+///
+/// A dealer 22 pushing and blackjack paying even money move the charts far enough from
+/// basic strategy that it's simpler to work the odds out each time. The same odds decide
+/// which way round to hold the two hands.
+pub struct SwitchStrategy;
+
+impl SwitchStrategy {
+    /// Whether swapping the second cards makes the better pair of hands
+    pub fn switch(game: &Game) -> bool {
+        let [first, second] = game.player_hands() else {
+            return false;
+        };
+        let (mut switched_first, mut switched_second) = (first.clone(), second.clone());
+        Hand::switch(&mut switched_first, &mut switched_second);
+
+        let kept = Self::hand_value(game, first) + Self::hand_value(game, second);
+        let switched = Self::hand_value(game, &switched_first) + Self::hand_value(game, &switched_second);
+        switched > kept
+    }
+
+    pub fn choice(game: &Game, hand: &Hand, dealer_card: &Card, choices: PlayerChoices) -> PlayerChoice {
+        let composition = game.shoe_composition();
+        let peeked = game.config.hole_card_rules.dealer_peeks();
+        match player_odds(game, &composition, dealer_card, None, peeked) {
+            Some(mut odds) => odds.best_choice(hand, choices),
+            None => BasicStrategy::choice(hand, dealer_card, choices),
+        }
+    }
+
+    /// Expected result of a freshly dealt hand, assuming the dealer doesn't have blackjack
+    fn hand_value(game: &Game, hand: &Hand) -> f64 {
+        if hand.is_blackjack() {
+            let (win, per) = game.config.payout_odds.ratio();
+            return win as f64 / per as f64;
+        }
+        let composition = game.shoe_composition();
+        let Some(mut odds) = player_odds(game, &composition, &game.dealer_up_card(), None, true) else {
+            return 0.0;
+        };
+        let mut choices = PlayerChoices::HIT | PlayerChoices::STAND | PlayerChoices::DOUBLE;
+        if hand.can_split(&game.config.split_matching) {
+            choices.insert(PlayerChoices::SPLIT);
+        }
+        odds.best_result(hand, choices)
+    }
+}
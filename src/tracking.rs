@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{game::Game, money::Money, shuffle::{ShuffleRecord, ShuffleRoutine}, types::MIN_CUT};
+
+/// Tracks segments of the discard tray through the dealer's shuffle
+///
+/// While a shoe is dealt the tracker keeps the Hi-Lo count of every segment of the discard
+/// tray. When the shoe is shuffled it works out where those segments are likely to land by
+/// running the casino's shuffle routine on the tray many times, the same way a tracker
+/// studies a shuffle before playing against it. It never sees the actual order of the cards.
+#[derive(Serialize, Deserialize)]
+pub struct ShuffleTracker {
+    /// Number of discards counted as one segment
+    pub segment_size: usize,
+    /// Number of upcoming cards a prediction covers, about one heads up round
+    pub lookahead: usize,
+    /// Number of practice shuffles used to learn where the segments end up
+    pub simulations: usize,
+    /// Expected Hi-Lo tag of every card of the current shoe in dealing order
+    shoe_map: Option<(usize, Vec<f64>)>,
+    /// Predicted and actual true count when the current bet was placed
+    pending: Option<(isize, isize)>,
+
+    pub report: TrackingReport,
+}
+
+impl Default for ShuffleTracker {
+    fn default() -> Self {
+        Self {
+            segment_size: 26,
+            lookahead: 6,
+            simulations: 100,
+            shoe_map: None,
+            pending: None,
+            report: TrackingReport::default(),
+        }
+    }
+}
+
+impl ShuffleTracker {
+    /// Expected true count of the next few cards, if the shoe was rebuilt from tracked discards
+    pub fn predicted_true_count(&mut self, game: &Game) -> Option<isize> {
+        let record = game.last_shuffle()?;
+        if self.shoe_map.as_ref().is_none_or(|(shoe, _)| *shoe != record.shoe) {
+            let map = self.map_shoe(record, &game.config.shuffle_routine);
+            self.shoe_map = Some((record.shoe, map));
+        }
+        let (_, map) = self.shoe_map.as_ref().unwrap();
+
+        let dealt = record.len().saturating_sub(game.cards_left().unwrap_or_default());
+        let upcoming: Vec<f64> = (dealt..(dealt + self.lookahead).min(map.len()))
+            // The cut moved the top of the shuffled pile to the bottom
+            .map(|position| map[(position + record.cut) % map.len()])
+            .collect();
+        if upcoming.is_empty() {
+            return None;
+        }
+        // Low cards tag +1, so a positive slug is bad for the player
+        let tags: f64 = upcoming.iter().sum();
+        let deck = game.config.deck_type.cards() as f64;
+        Some((-tags * deck / upcoming.len() as f64).round() as isize)
+    }
+
+    /// Bets on the predicted count of the upcoming cards, falling back to the true count
+    pub fn bet(&mut self, game: &Game) -> Money {
+        let true_count = game.true_count();
+        let predicted = self.predicted_true_count(game).unwrap_or(true_count);
+        self.pending = Some((predicted, true_count));
+
+        crate::strategy::BASE_BET * tracking_units(predicted)
+    }
+
+    /// Records the result of the round that was just settled
+    pub fn hand_over(&mut self, game: &Game) {
+        if let Some((predicted, true_count)) = self.pending.take() {
+            let units = game.initial_wager();
+            if units > Money::ZERO {
+                let result = game.main_bet_net() as f64 / units.cents() as f64;
+                self.report.record(predicted, true_count, result);
+            }
+        }
+    }
+
+    fn map_shoe(&self, record: &ShuffleRecord, routine: &ShuffleRoutine) -> Vec<f64> {
+        // Every card takes the average tag of its segment, that's all the tracker remembers
+        let mut tags = Vec::with_capacity(record.len());
+        for segment in record.discards.chunks(self.segment_size.max(1)) {
+            let count: isize = segment.iter().map(|card| card.count()).sum();
+            let average = count as f64 / segment.len() as f64;
+            tags.extend(std::iter::repeat_n(average, segment.len()));
+        }
+        // Hi-Lo is balanced, so the unseen cards make up whatever the discards left over
+        let running_count: isize = record.discards.iter().map(|card| card.count()).sum();
+        if record.unseen > 0 {
+            let average = -running_count as f64 / record.unseen as f64;
+            tags.extend(std::iter::repeat_n(average, record.unseen));
+        }
+
+        expected_through_shuffle(&tags, routine, self.simulations)
+    }
+
+    /// Cuts the shoe so the segments richest in low cards end up behind the cut card
+    pub fn cut(&mut self, game: &Game) -> Option<usize> {
+        self.predicted_true_count(game)?;
+        let (_, map) = self.shoe_map.as_ref()?;
+        // Assume about three quarters of the shoe gets dealt
+        let in_play = map.len() * 3 / 4;
+        best_cut(map, in_play, |window| -window.iter().sum::<f64>())
+    }
+}
+
+/// Runs a shuffle routine over a pile many times and returns the average value that ends
+/// up at every position of the new shoe, in dealing order
+pub fn expected_through_shuffle(values: &[f64], routine: &ShuffleRoutine, simulations: usize) -> Vec<f64> {
+    let mut map = vec![0.0; values.len()];
+    for _ in 0..simulations {
+        let mut pile: Vec<usize> = (0..values.len()).collect();
+        routine.apply(&mut pile);
+        // The top of the pile is dealt first
+        for (position, index) in pile.iter().rev().enumerate() {
+            map[position] += values[*index];
+        }
+    }
+    for value in map.iter_mut() {
+        *value /= simulations.max(1) as f64;
+    }
+    map
+}
+
+/// Picks the legal cut that scores the first `dealt` cards of the cut shoe highest
+///
+/// A cut `position` cards from the top moves those cards to the bottom, so the shoe is then
+/// dealt starting from `map[position]`.
+pub fn best_cut(map: &[f64], dealt: usize, score: impl Fn(&[f64]) -> f64) -> Option<usize> {
+    let total = map.len();
+    if total <= 2 * MIN_CUT {
+        return None;
+    }
+    let doubled: Vec<f64> = map.iter().chain(map.iter()).copied().collect();
+    (MIN_CUT..=total - MIN_CUT)
+        .map(|position| (position, score(&doubled[position..position + dealt.min(total)])))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(position, _)| position)
+}
+
+/// Bet units for a predicted true count, never leaving the table mid-shoe
+fn tracking_units(true_count: isize) -> u32 {
+    match true_count {
+        tc if tc <= 0 => 1,
+        1 => 2,
+        2 => 4,
+        3 => 6,
+        _ => 8,
+    }
+}
+
+/// Compares betting on tracked segments against betting on the running count
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct TrackingReport {
+    /// Predicted count, true count and result in units of the initial bet for every round
+    rounds: Vec<(isize, isize, f64)>,
+}
+
+impl TrackingReport {
+    pub fn record(&mut self, predicted: isize, true_count: isize, result: f64) {
+        self.rounds.push((predicted, true_count, result));
+    }
+
+    /// Edge of the tracking bet spread when it is driven by the true count
+    pub fn counting_edge(&self) -> f64 {
+        self.edge_with(|(_, true_count, _)| *true_count)
+    }
+
+    /// Edge of the tracking bet spread when it is driven by the predicted count
+    pub fn tracking_edge(&self) -> f64 {
+        self.edge_with(|(predicted, _, _)| *predicted)
+    }
+
+    /// Edge of a flat bet
+    pub fn flat_edge(&self) -> f64 {
+        self.edge_with(|_| 0)
+    }
+
+    /// How often the tracked prediction and the true count disagree on raising the bet
+    pub fn disagreement(&self) -> f64 {
+        if self.rounds.is_empty() {
+            return 0.0;
+        }
+        let disagree = self.rounds.iter()
+            .filter(|(predicted, true_count, _)| (*predicted > 0) != (*true_count > 0))
+            .count();
+        disagree as f64 / self.rounds.len() as f64
+    }
+
+    fn edge_with(&self, signal: impl Fn(&(isize, isize, f64)) -> isize) -> f64 {
+        let mut wagered = 0.0;
+        let mut net = 0.0;
+        for round in &self.rounds {
+            let units = tracking_units(signal(round)) as f64;
+            wagered += units;
+            net += units * round.2;
+        }
+        if wagered == 0.0 {
+            return 0.0;
+        }
+        net / wagered
+    }
+}
+
+impl std::fmt::Display for TrackingReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counting = self.counting_edge();
+        let tracking = self.tracking_edge();
+        writeln!(f, "Tracked rounds: {}, Prediction disagrees with count: {:.1}%", self.rounds.len(), self.disagreement() * 100.0)?;
+        write!(
+            f,
+            "Edge flat: {:.2}%, betting the count: {:.2}%, betting tracked segments: {:.2}%, gained: {:.2}%",
+            self.flat_edge() * 100.0,
+            counting * 100.0,
+            tracking * 100.0,
+            (tracking - counting) * 100.0
+        )
+    }
+}
@@ -1,24 +1,34 @@
 use std::fmt::{Display, Formatter};
 
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
 
-use crate::error::Error;
+use crate::{config::{DeckType, SplitMatching}, error::Error, money::Money, shuffle::{ShuffleRecord, ShuffleRoutine}, sidebets::SideCount};
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum HandResult {
     Blackjack,
     Win,
     Lose,
     Push,
+    /// Half the bet is returned
+    Surrender,
+    /// The original bet is lost and the double is returned
+    Rescue,
     NotFinished,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum PlayerChoice {
     Hit,
     Stand,
     Double,
     Split,
     Surrender,
+    /// Double for the given amount, up to the original bet
+    DoubleForLess(Money),
+    /// Give up a doubled hand, losing the original bet but keeping the double
+    Rescue,
 }
 
 impl PlayerChoice {
@@ -29,6 +39,9 @@ impl PlayerChoice {
             "D" | "3" => PlayerChoice::Double,
             "P" | "4" => PlayerChoice::Split,
             "R" | "5" => PlayerChoice::Surrender,
+            // The amount is asked for separately
+            "L" | "6" => PlayerChoice::DoubleForLess(Money::ZERO),
+            "E" | "7" => PlayerChoice::Rescue,
             x => return Err(Error::InvalidInput(x.to_string())),
         };
         if choices.contains(choice.into()) {
@@ -47,18 +60,23 @@ impl std::fmt::Display for PlayerChoice {
             PlayerChoice::Double => write!(f, "Double Down"),
             PlayerChoice::Split => write!(f, "Split"),
             PlayerChoice::Surrender => write!(f, "Surrender"),
+            PlayerChoice::DoubleForLess(amount) => write!(f, "Double for {}", amount),
+            PlayerChoice::Rescue => write!(f, "Rescue"),
         }
     }
 }
 
 bitflags::bitflags! {
-    #[derive(Clone, Copy)]
+    /// Written as the flag names, such as `HIT | STAND | DOUBLE`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
     pub struct PlayerChoices: u8 {
         const HIT = 0b0001;
         const STAND = 0b0010;
         const DOUBLE = 0b0100;
         const SPLIT = 0b1000;
         const SURRENDER = 0b0001_0000;
+        const DOUBLE_FOR_LESS = 0b0010_0000;
+        const RESCUE = 0b0100_0000;
     }
 }
 
@@ -70,78 +88,139 @@ impl From<PlayerChoice> for PlayerChoices {
             PlayerChoice::Double => PlayerChoices::DOUBLE,
             PlayerChoice::Split => PlayerChoices::SPLIT,
             PlayerChoice::Surrender => PlayerChoices::SURRENDER,
+            PlayerChoice::DoubleForLess(_) => PlayerChoices::DOUBLE_FOR_LESS,
+            PlayerChoice::Rescue => PlayerChoices::RESCUE,
         }
     }
 }
 
+/// Casinos make the player cut at least half a deck from either end of the shoe
+pub const MIN_CUT: usize = 26;
+
+/// Positions a shoe of `total_cards` can be cut at, anywhere in a shoe too small for the
+/// minimum
+pub fn cut_range(total_cards: usize) -> std::ops::RangeInclusive<usize> {
+    if total_cards > 2 * MIN_CUT {
+        MIN_CUT..=total_cards - MIN_CUT
+    } else {
+        0..=total_cards
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Deck {
     pub cards: Vec<Card>,
-    pub count: isize
+    pub count: isize,
+    /// Number of decks the shoe is built from
+    pub decks: usize,
+    /// Kind of deck the shoe is built from
+    #[serde(default)]
+    pub deck_type: DeckType,
+    /// Set once the cut card comes out and the shoe is due for a shuffle
+    pub cut_card_drawn: bool,
+    /// Cards played since the last shuffle in the order they were picked up
+    pub discards: Vec<Card>,
+    /// How the dealer shuffles the discards into the next shoe
+    pub shuffle_routine: ShuffleRoutine,
+    /// The pile the current shoe was shuffled from, if it was built from discards
+    pub last_shuffle: Option<ShuffleRecord>,
+    /// Set after a shuffle until a player cuts the shoe
+    pub awaiting_cut: bool,
+    /// Side count kept alongside the Hi-Lo count, if the table keeps one
+    #[serde(default)]
+    pub side_count: Option<SideCount>,
 }
 
 impl Deck {
+    /// A single unshuffled deck of the given type
+    pub fn of_type(deck_type: &DeckType) -> Self {
+        let mut deck = Deck { deck_type: deck_type.clone(), ..Deck::default() };
+        if matches!(deck_type, DeckType::Spanish) {
+            deck.cards.retain(|card| card.face != CardFace::Number(10));
+        }
+        deck
+    }
+
     /// Creates a shoe of cards with the specified number of decks.
-    pub fn create_shoe(decks: usize) -> Self {
+    pub fn create_shoe(decks: usize, deck_type: &DeckType) -> Self {
         let mut cards = Vec::new();
         for _ in 0..decks {
-            let deck = Deck::default();
+            let deck = Deck::of_type(deck_type);
             cards.extend(deck.cards);
         }
         cards.shuffle(&mut thread_rng());
-        let mut this = Self { cards, count: 0 };
-        this.place_cut_card();
-        this
-    }
-
-    pub fn deal_hand(&mut self, hand_size: usize, dealer: bool) -> (Hand, bool) {
-        let mut reshuffle_deck = false;
-        let mut hand = Hand::new(dealer);
-        for _ in 0..hand_size {
-            let (card, cut_card) = self.draw();
-            reshuffle_deck |= cut_card;
-            hand.push(card);
+        Self {
+            cards,
+            count: 0,
+            decks,
+            deck_type: deck_type.clone(),
+            cut_card_drawn: false,
+            discards: Vec::new(),
+            shuffle_routine: ShuffleRoutine::Uniform,
+            last_shuffle: None,
+            awaiting_cut: true,
+            side_count: None,
         }
-        hand.cards.reverse(); // Reverse to maintain the order of dealing
+    }
 
-        (hand, reshuffle_deck)
+    /// Builds the next shoe from the discards and the cards left behind the cut card
+    pub fn shuffle_discards(&mut self) {
+        let discards = std::mem::take(&mut self.discards);
+        let mut pile = discards.clone();
+        // The unplayed cards go on top of the discard pile
+        pile.extend(self.cards.drain(..).filter(|card| !card.cut_card));
+        let shoe = self.last_shuffle.as_ref().map_or(1, |record| record.shoe + 1);
+        self.last_shuffle = Some(ShuffleRecord { shoe, unseen: pile.len() - discards.len(), discards, cut: 0 });
+
+        self.shuffle_routine.apply(&mut pile);
+
+        self.cards = pile;
+        self.count = 0;
+        if let Some(side_count) = self.side_count.as_mut() {
+            side_count.running_count = 0;
+        }
+        self.cut_card_drawn = false;
+        self.awaiting_cut = true;
     }
-    /// Draw a card and bool is true if the cut card was drawn
-    pub fn draw(&mut self) -> (Card, bool) {
-        let card = self.cards.pop().unwrap();
-        let card_count = card.count();
-        self.count += card_count; // Update the count based on the card drawn
 
-        if card.cut_card {
-            // Take the next card after the cut card
-            (self.cards.pop().unwrap(), true)
-        } else {
-            (card, false)
+    /// A player cuts the shoe `position` cards from the top, or at a random spot for `None`
+    ///
+    /// The cards above the cut go to the bottom, then the dealer places the cut card.
+    pub fn cut(&mut self, position: Option<usize>) {
+        let range = cut_range(self.cards.len());
+        let position = position
+            .unwrap_or_else(|| thread_rng().gen_range(range.clone()))
+            .clamp(*range.start(), *range.end());
+
+        // The top of the shoe is the end of the cards
+        self.cards.rotate_right(position);
+        if let Some(record) = self.last_shuffle.as_mut() {
+            record.cut = position;
         }
+        self.place_cut_card();
+        self.awaiting_cut = false;
     }
+
     /// Assumes new cards were just dealt
     fn place_cut_card(&mut self) {
         let total_cards = self.cards.len();
-        let decks = total_cards / 52;
-        let position = match decks {
+        let deck_cards = self.deck_type.cards();
+        let position = match total_cards / deck_cards {
             0 => panic!("Cannot place cut card in a deck with no cards"),
             // 3/4 of the deck
-            1 => total_cards - 39,
+            1 => total_cards - deck_cards * 3 / 4,
             // Leave about half a deck
-            2 => total_cards - 26,
+            2 => total_cards - deck_cards / 2,
             // 3-5 decks leave 1 deck
-            3..=5 => total_cards - 52,
+            3..=5 => total_cards - deck_cards,
             // 6+ decks leave 1.5 decks
-            _ => total_cards - 78,
+            _ => total_cards - deck_cards * 3 / 2,
         };
-        let random_offset = (rand::random::<isize>() % 11) - 5; // Random offset between -5 and 5
+        let random_offset = thread_rng().gen_range(-5..=5);
         let cut_card_position = (position as isize + random_offset) as usize;
         let cut_card_position = total_cards - cut_card_position;
 
-        self.cards.insert(cut_card_position, Card {
-            suit: Suit::Spades, // Cut card doesn't have a suit
-            face: CardFace::Face(Face::King), // Just a placeholder
-            cut_card: true,
-        });
+        self.cards.insert(cut_card_position, Card::cut_card());
     }
 }
 
@@ -152,14 +231,37 @@ impl std::ops::Add for Deck {
         let mut cards = self.cards;
         cards.extend(other.cards);
 
-        Self { cards, count: self.count + other.count }
+        let mut discards = self.discards;
+        discards.extend(other.discards);
+
+        Self {
+            cards,
+            count: self.count + other.count,
+            decks: self.decks + other.decks,
+            deck_type: self.deck_type,
+            cut_card_drawn: self.cut_card_drawn || other.cut_card_drawn,
+            discards,
+            shuffle_routine: self.shuffle_routine,
+            last_shuffle: None,
+            awaiting_cut: self.awaiting_cut || other.awaiting_cut,
+            side_count: self.side_count,
+        }
     }
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct Hand {
+    /// Cards in the order they were dealt
     pub cards: Vec<Card>,
     pub hide_card: bool,
+    /// The hand was made by splitting a pair, so two cards making 21 are not a blackjack
+    pub from_split: bool,
+    /// The hand's second card was switched in, so two cards making 21 are not a blackjack
+    #[serde(default)]
+    pub switched: bool,
+    /// The starting hand this hand was dealt as or split from
+    #[serde(default)]
+    pub starting_hand: usize,
 }
 
 impl Hand {
@@ -167,54 +269,33 @@ impl Hand {
         Self {
             cards: Vec::new(),
             hide_card: dealer_hand,
+            from_split: false,
+            switched: false,
+            starting_hand: 0,
         }
     }
 
     pub fn value(&self) -> u8 {
-        let mut value = 0;
-        let mut aces = 0;
-        for card in self.cards.iter() {
-            match card.face {
-                CardFace::Ace => aces += 1,
-                CardFace::Number(n) => value += n,
-                CardFace::Face(_) => value += 10,
-            }
-        }
-        for _ in 0..aces {
-            if value + 11 <= 21 {
-                value += 11;
-            } else {
-                value += 1;
-            }
+        let hard = self.hard_value();
+        if self.is_soft() {
+            hard + 10
+        } else {
+            hard
         }
-        value
     }
+    /// Hand is soft if an ace can be counted as 11 without busting
     pub fn is_soft(&self) -> bool {
-        let mut value = 0;
-        let mut aces = 0;
-        let mut aces_as_eleven = 0;
-        
-        // Calculate non-ace values first
-        for card in self.cards.iter() {
-            match card.face {
-                CardFace::Ace => aces += 1,
-                CardFace::Number(n) => value += n,
-                CardFace::Face(_) => value += 10,
-            }
-        }
-        
-        // Add aces, tracking how many are counted as 11
-        for _ in 0..aces {
-            if value + 11 <= 21 {
-                value += 11;
-                aces_as_eleven += 1;
-            } else {
-                value += 1;
-            }
-        }
-        
-        // Hand is soft if at least one ace is counted as 11
-        aces_as_eleven > 0
+        self.cards.iter().any(|card| card.face == CardFace::Ace) && self.hard_value() + 10 <= 21
+    }
+    /// Value with every ace counted as 1
+    fn hard_value(&self) -> u8 {
+        self.cards.iter()
+            .map(|card| match card.face {
+                CardFace::Ace => 1,
+                CardFace::Number(n) => n,
+                CardFace::Face(_) => 10,
+            })
+            .sum()
     }
     fn show_value(&self) -> u8 {
         if self.hide_card {
@@ -224,39 +305,60 @@ impl Hand {
     }
 
     pub fn is_blackjack(&self) -> bool {
-        self.cards.len() == 2 && self.value() == 21 && self.cards.iter().any(|card| card.face == CardFace::Ace)
+        !self.from_split && !self.switched && self.cards.len() == 2 && self.value() == 21 && self.cards.iter().any(|card| card.face == CardFace::Ace)
     }
     pub fn is_bust(&self) -> bool {
         self.value() > 21
     }
 
-    pub fn can_split(&self) -> bool {
+    pub fn can_split(&self, matching: &SplitMatching) -> bool {
         if self.cards.len() != 2 {
             return false;
         }
-        &self.cards[0] == &self.cards[1]
+        match matching {
+            SplitMatching::SameRank => self.cards[0] == self.cards[1],
+            SplitMatching::SameValue => self.cards[0].face_value() == self.cards[1].face_value(),
+        }
     }
 
+    /// The card the pair is made of, any two ten-value cards are the same pair of tens
     pub fn splitable_card(&self) -> Option<CardFace> {
-        if self.can_split() {
-            Some(self.cards[0].clone().face)
-        } else {
-            None
+        match self.cards.as_slice() {
+            [first, second] if first.face_value() == second.face_value() => {
+                if first.face_value() == 10 {
+                    Some(CardFace::Number(10))
+                } else {
+                    Some(first.face.clone())
+                }
+            }
+            _ => None,
         }
     }
 
     pub fn split(mut self) -> (Self, Self) {
-        let card = self.cards.pop().unwrap();
-        let hand1 = Hand { cards: vec![card.clone()], hide_card: self.hide_card };
-        let hand2 = Hand { cards: vec![card], hide_card: self.hide_card };
+        let second = self.cards.pop().unwrap();
+        let first = self.cards.pop().unwrap();
+        let hand1 = Hand { cards: vec![first], from_split: true, ..self.clone() };
+        let hand2 = Hand { cards: vec![second], from_split: true, ..self };
         (hand1, hand2)
     }
+    /// Swaps the second cards of two hands, as in Blackjack Switch
+    pub fn switch(first: &mut Hand, second: &mut Hand) {
+        std::mem::swap(&mut first.cards[1], &mut second.cards[1]);
+        first.switched = true;
+        second.switched = true;
+    }
+    /// A hand made by splitting aces
+    pub fn is_split_aces(&self) -> bool {
+        self.from_split && self.cards.first().is_some_and(|card| card.face == CardFace::Ace)
+    }
 
     pub fn push(&mut self, card: Card) {
         self.cards.push(card);
     }
+    /// The dealer's up card is the first card dealt
     pub fn dealer_show_card(&self) -> &Card {
-        &self.cards[1]
+        &self.cards[0]
     }
 }
 
@@ -288,7 +390,18 @@ impl Default for Deck {
                 });
             }
         }
-        Self { cards, count: 0 }
+        Self {
+            cards,
+            count: 0,
+            decks: 1,
+            deck_type: DeckType::Standard,
+            cut_card_drawn: false,
+            discards: Vec::new(),
+            shuffle_routine: ShuffleRoutine::Uniform,
+            last_shuffle: None,
+            awaiting_cut: false,
+            side_count: None,
+        }
     }
 }
 
@@ -300,6 +413,14 @@ pub struct Card {
 }
 
 impl Card {
+    /// The plastic card that marks where the dealer stops dealing the shoe
+    pub fn cut_card() -> Self {
+        Card {
+            suit: Suit::Spades, // Cut card doesn't have a suit
+            face: CardFace::Face(Face::King), // Just a placeholder
+            cut_card: true,
+        }
+    }
     pub fn face_value(&self) -> u8 {
         match self.face {
             CardFace::Ace => 11, // Ace is worth 11 by default
@@ -307,6 +428,10 @@ impl Card {
             CardFace::Face(_) => 10, // Face cards are worth 10
         }
     }
+    /// Same rank and suit, unlike `==` which only compares the rank
+    pub fn is_same_card(&self, other: &Card) -> bool {
+        self.face == other.face && self.suit == other.suit
+    }
     pub fn is_blackjack_card(&self) -> bool {
         self.face == CardFace::Ace || matches!(self.face, CardFace::Face(_))
     }
@@ -326,13 +451,62 @@ impl Card {
     }
 }
 
+impl std::str::FromStr for Card {
+    type Err = Error;
+
+    /// Parses a rank followed by a suit, such as `A♠`, `Th`, `10♥` or `qd`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || Error::InvalidInput(format!("Invalid card: {}", s));
+        let suit_char = s.chars().last().ok_or_else(invalid)?;
+        let rank = &s[..s.len() - suit_char.len_utf8()];
+
+        let suit = match suit_char.to_ascii_uppercase() {
+            '♥' | '♡' | 'H' => Suit::Hearts,
+            '♦' | '♢' | 'D' => Suit::Diamonds,
+            '♣' | '♧' | 'C' => Suit::Clubs,
+            '♠' | '♤' | 'S' => Suit::Spades,
+            _ => return Err(invalid()),
+        };
+        let face = match rank.to_uppercase().as_str() {
+            "A" => CardFace::Ace,
+            "T" | "10" => CardFace::Number(10),
+            "J" => CardFace::Face(Face::Jack),
+            "Q" => CardFace::Face(Face::Queen),
+            "K" => CardFace::Face(Face::King),
+            n => match n.parse::<u8>() {
+                Ok(n @ 2..=9) => CardFace::Number(n),
+                _ => return Err(invalid()),
+            },
+        };
+        Ok(Card { suit, face, cut_card: false })
+    }
+}
+
+/// Cards are written the way they're shown, such as `A♠` or `10♥`
+impl Serialize for Card {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Card {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let card = String::deserialize(deserializer)?;
+        if card == "CUT" {
+            return Ok(Card::cut_card());
+        }
+        card.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl PartialEq for Card {
     fn eq(&self, other: &Self) -> bool {
         self.face == other.face
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Suit {
     Hearts,
     Diamonds,
@@ -340,14 +514,14 @@ pub enum Suit {
     Spades,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum CardFace {
     Ace,
     Number(u8),
     Face(Face),
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Face {
     Jack,
     Queen,